@@ -0,0 +1,68 @@
+//! Pinning smithay-clipboard's background thread to a CPU core.
+//!
+//! The thread is spawned inside `Clipboard::new`, so there's no handle to set
+//! its affinity through. New threads inherit their creator's affinity though,
+//! so the creating thread is pinned for the duration of the call and then
+//! restored.
+
+use crate::diagnostics::{Diagnostic, DiagnosticSink};
+
+/// Run `create` with the current thread pinned to `core`.
+#[cfg(target_os = "linux")]
+pub fn pinned<T>(core: usize, sink: &mut dyn DiagnosticSink, create: impl FnOnce() -> T) -> T {
+    use rustix::thread::{sched_getaffinity, sched_setaffinity, CpuSet};
+
+    if core >= CpuSet::MAX_CPU {
+        sink.emit(Diagnostic::warn(
+            "pin-failed",
+            format!(
+                "Can't pin to core {core}: cores go up to {}",
+                CpuSet::MAX_CPU - 1
+            ),
+        ));
+        return create();
+    }
+    let previous = match sched_getaffinity(None) {
+        Ok(previous) => previous,
+        Err(err) => {
+            sink.emit(Diagnostic::warn(
+                "pin-failed",
+                format!("Can't read CPU affinity, not pinning: {err}"),
+            ));
+            return create();
+        }
+    };
+
+    let mut affinity = CpuSet::new();
+    affinity.set(core);
+    if let Err(err) = sched_setaffinity(None, &affinity) {
+        sink.emit(Diagnostic::warn(
+            "pin-failed",
+            format!("Can't pin to core {core}: {err}"),
+        ));
+        return create();
+    }
+    sink.emit(
+        Diagnostic::info("pinned", format!("Pinned clipboard thread to core {core}"))
+            .field("core", core),
+    );
+
+    let result = create();
+    if let Err(err) = sched_setaffinity(None, &previous) {
+        sink.emit(Diagnostic::warn(
+            "pin-restore-failed",
+            format!("Can't restore CPU affinity: {err}"),
+        ));
+    }
+    result
+}
+
+/// Run `create` unpinned; affinity isn't supported on this platform.
+#[cfg(not(target_os = "linux"))]
+pub fn pinned<T>(core: usize, sink: &mut dyn DiagnosticSink, create: impl FnOnce() -> T) -> T {
+    sink.emit(Diagnostic::warn(
+        "pin-unsupported",
+        format!("Pinning to core {core} is only supported on Linux, ignoring --pin-worker"),
+    ));
+    create()
+}
@@ -0,0 +1,1111 @@
+//! The winit application that reproduces the segfault.
+
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use winit::application::ApplicationHandler;
+use winit::event::{ElementState, WindowEvent};
+use winit::event_loop::{ActiveEventLoop, ControlFlow, EventLoop};
+use winit::keyboard::Key;
+use winit::raw_window_handle::{HasDisplayHandle, RawDisplayHandle};
+use winit::window::{Window, WindowAttributes, WindowId};
+
+use crate::affinity;
+use crate::config::{effective_timeout, timeout_scale, ClipboardConfig};
+use crate::diagnostics::{Diagnostic, DiagnosticSink};
+use crate::record::{Event, Recorder, Replay};
+use crate::timing::{Op, OpTimings};
+
+const WATCH_FOR_SEGFAULT: &str = "Watch for SEGFAULT - this is 100% safe Rust code!";
+
+/// How often `--watch-external` and `--wait-for-owner` read the clipboard.
+const WATCH_INTERVAL: Duration = Duration::from_millis(250);
+
+/// The slowest a healthy run iterates its event loop. `ControlFlow::Poll`
+/// is normally orders of magnitude faster, so a run that handled fewer
+/// events than its timeout allows at this rate never really got going.
+const MIN_ITERATION_RATE: Duration = Duration::from_millis(10);
+
+/// How often `--trace-stdout` reports each kind of clipboard operation.
+/// Hold and burn-in make thousands, which would bury the teardown.
+const OP_TRACE_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Why the event loop stopped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShutdownReason {
+    /// The configured timeout elapsed.
+    Timeout,
+    /// A `--burn-in` run finished.
+    BurnIn,
+    /// The `--frames` limit was reached.
+    FrameLimit,
+    /// The window was closed.
+    CloseRequested,
+    /// A replayed session reached its recorded exit.
+    Replay,
+    /// Ctrl+C was pressed.
+    Interrupted,
+    /// `--wait-for-owner` found content on the clipboard.
+    ContentAvailable,
+    /// `--wait-for-owner` gave up after `--wait-timeout`.
+    WaitTimeout,
+    /// Something the run depends on failed, e.g. creating the window.
+    Error,
+}
+
+impl ShutdownReason {
+    /// Whether the binary should exit with a nonzero status.
+    pub fn is_failure(self) -> bool {
+        matches!(self, ShutdownReason::Error | ShutdownReason::WaitTimeout)
+    }
+}
+
+/// Creates the run's window. [`run_with`] takes one so tests can make window
+/// creation fail.
+pub type WindowFactory = fn(&ActiveEventLoop, WindowAttributes) -> io::Result<Window>;
+
+/// The [`WindowFactory`] [`run_once`] uses.
+fn create_window(event_loop: &ActiveEventLoop, attributes: WindowAttributes) -> io::Result<Window> {
+    event_loop
+        .create_window(attributes)
+        .map_err(io::Error::other)
+}
+
+/// What happened during a [`run_once`].
+#[derive(Debug, Clone)]
+pub struct RunOutcome {
+    /// Number of event loop callbacks the app handled.
+    pub event_count: u64,
+    /// Number of `RedrawRequested` frames handled.
+    pub frame_count: u64,
+    /// `None` if the loop stopped without the app asking it to.
+    pub shutdown_reason: Option<ShutdownReason>,
+    /// Whether a clipboard was ever created. Off Wayland, or when the window
+    /// fails, there is none, and `clipboard_alive` is trivially false.
+    pub clipboard_created: bool,
+    /// Whether the clipboard was still alive when the event loop exited,
+    /// i.e. whether it is about to outlive the display.
+    pub clipboard_alive: bool,
+    /// Whether the run stopped on its own well before the timeout could
+    /// have elapsed, as when the window never maps in a headless session.
+    /// Runs the user or a flag ended on purpose are never flagged.
+    pub crashed_early: bool,
+    /// Threads in the process around the last clipboard creation, if
+    /// `--count-threads` was on and the platform supports counting them.
+    pub thread_counts: Option<ThreadCounts>,
+    op_timings: OpTimings,
+}
+
+/// Threads in the process just before and just after creating a clipboard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ThreadCounts {
+    pub before: usize,
+    pub after: usize,
+}
+
+impl ThreadCounts {
+    /// How many threads creating the clipboard spawned.
+    pub fn spawned(self) -> i64 {
+        self.after as i64 - self.before as i64
+    }
+}
+
+impl RunOutcome {
+    /// How long the run's clipboard operations took.
+    pub fn op_timings(&self) -> &OpTimings {
+        &self.op_timings
+    }
+}
+
+/// Whether a run that handled `event_count` events and stopped for `reason`
+/// exited before doing anything, given it was meant to last `timeout`.
+fn crashed_early(event_count: u64, reason: Option<ShutdownReason>, timeout: Duration) -> bool {
+    let meant_to_last = matches!(
+        reason,
+        None | Some(ShutdownReason::Timeout | ShutdownReason::Error)
+    );
+    let expected = timeout.as_millis() / MIN_ITERATION_RATE.as_millis();
+    meant_to_last && u128::from(event_count) < expected
+}
+
+struct App {
+    config: ClipboardConfig,
+    sink: Box<dyn DiagnosticSink>,
+    create_window: WindowFactory,
+    // Window declared BEFORE clipboard - this means window drops LAST.
+    // This is the "wrong" order that triggers the bug, but it's completely
+    // reasonable code that a user might write. Nothing warns about this.
+    //
+    // NOTE: Swapping the order of `window` and `clipboard` fields would
+    // "fix" the segfault because Rust drops fields in declaration order.
+    // But that's exactly the point - a safe API should not segfault based
+    // on field ordering! The compiler gives no warning about this.
+    window: Option<Arc<Window>>,
+    clipboard: Option<egui_winit::clipboard::Clipboard>,
+    clipboard_created: bool,
+    start_time: Option<Instant>,
+    // When the run started, which is what recorded times are relative to
+    origin: Instant,
+    // Lifecycle log being written (`--record`) or played back (`--replay`).
+    recorder: Option<Recorder>,
+    replay: Option<Replay>,
+    event_count: u64,
+    frame_count: u64,
+    shutdown_reason: Option<ShutdownReason>,
+    burn_in: BurnIn,
+    watch: Watch,
+    op_timings: OpTimings,
+    thread_counts: Option<ThreadCounts>,
+    // Writes `--max-bytes` truncated or rejected
+    oversize_writes: u64,
+    // Indexed by `Op`
+    op_traces: [OpTrace; 2],
+}
+
+/// Rate limit for tracing one kind of clipboard operation.
+#[derive(Default)]
+struct OpTrace {
+    last: Option<Instant>,
+    // Operations since `last` that weren't traced
+    skipped: u64,
+}
+
+impl OpTrace {
+    /// Whether an operation at `now` should be traced, and if so how many
+    /// were skipped before it.
+    fn due(&mut self, now: Instant) -> Option<u64> {
+        if self
+            .last
+            .is_some_and(|last| now.duration_since(last) < OP_TRACE_INTERVAL)
+        {
+            self.skipped += 1;
+            return None;
+        }
+        self.last = Some(now);
+        Some(std::mem::take(&mut self.skipped))
+    }
+}
+
+/// State of a `--watch-external` or `--wait-for-owner` run.
+#[derive(Default)]
+struct Watch {
+    // Set from the Ctrl+C handler
+    interrupted: Arc<AtomicBool>,
+    last_poll: Option<Instant>,
+    last_seen: Option<String>,
+}
+
+/// Progress of a `--burn-in` run.
+#[derive(Default)]
+struct BurnIn {
+    iterations: u64,
+    // Iterations whose clipboard read back what was just written
+    clean: u64,
+    reported: u64,
+}
+
+impl App {
+    /// Number of threads in this process, sampled from `/proc/self/task`.
+    ///
+    /// Returns 0 when that directory isn't available, e.g. on non-Linux.
+    fn thread_count() -> usize {
+        std::fs::read_dir("/proc/self/task")
+            .map(|tasks| tasks.count())
+            .unwrap_or(0)
+    }
+
+    fn record(&mut self, event: Event) {
+        let Some(recorder) = &mut self.recorder else {
+            return;
+        };
+        if let Err(err) = recorder.record(event) {
+            self.emit(
+                Diagnostic::warn(
+                    "record-failed",
+                    format!("Failed to record {}: {err}", event.name()),
+                )
+                .field("event", event.name()),
+            );
+        }
+    }
+
+    /// Write `text` to the clipboard, if there is one, timing and recording
+    /// the call.
+    fn clipboard_set(&mut self, text: String) {
+        if self.clipboard.is_none() {
+            return;
+        }
+        let Some(text) = self.fit_write(text) else {
+            return;
+        };
+        if let Some(clipboard) = &mut self.clipboard {
+            let bytes = text.len();
+            self.op_timings.time(Op::Set, || clipboard.set_text(text));
+            self.record(Event::ClipboardSet);
+            self.trace_op(Op::Set, Some(bytes));
+        }
+    }
+
+    /// Apply `--max-bytes` to a write, per `--on-oversize`. Only the first
+    /// oversized write is reported, since hold and burn-in make thousands.
+    fn fit_write(&mut self, text: String) -> Option<String> {
+        let Some(max_bytes) = self.config.max_bytes else {
+            return Some(text);
+        };
+        let len = text.len();
+        let fitted = self.config.on_oversize.fit(text, max_bytes);
+        if fitted.as_ref().is_none_or(|fitted| fitted.len() < len) {
+            self.oversize_writes += 1;
+            if self.oversize_writes == 1 {
+                let action = match fitted {
+                    Some(_) => "truncating",
+                    None => "rejecting",
+                };
+                self.emit(
+                    Diagnostic::warn(
+                        "oversize",
+                        format!("{len}-byte write is over --max-bytes {max_bytes}, {action} it"),
+                    )
+                    .field("bytes", len)
+                    .field("max_bytes", max_bytes),
+                );
+            }
+        }
+        fitted
+    }
+
+    /// Read the clipboard, if there is one, timing and recording the call.
+    fn clipboard_get(&mut self) -> Option<String> {
+        let clipboard = self.clipboard.as_mut()?;
+        let text = self.op_timings.time(Op::Get, || clipboard.get());
+        self.record(Event::ClipboardGet);
+        self.trace_op(Op::Get, text.as_ref().map(String::len));
+        text
+    }
+
+    /// Report a clipboard operation under `--trace-stdout`, at most once per
+    /// [`OP_TRACE_INTERVAL`] for each kind.
+    fn trace_op(&mut self, op: Op, bytes: Option<usize>) {
+        if !self.config.trace_stdout {
+            return;
+        }
+        let Some(skipped) = self.op_traces[op as usize].due(Instant::now()) else {
+            return;
+        };
+        let (event, verb) = match op {
+            Op::Set => ("clipboard-set", "Set"),
+            Op::Get => ("clipboard-get", "Read"),
+        };
+        let mut message = match bytes {
+            Some(bytes) => format!("{verb} {bytes} bytes"),
+            None => format!("{verb} nothing"),
+        };
+        if skipped > 0 {
+            message.push_str(&format!(" ({skipped} more since the last trace)"));
+        }
+        let mut diagnostic = Diagnostic::info(event, message).field("skipped", skipped);
+        if let Some(bytes) = bytes {
+            diagnostic = diagnostic.field("bytes", bytes);
+        }
+        self.emit(diagnostic);
+    }
+
+    fn create_clipboard(&mut self, raw_display: Option<RawDisplayHandle>) {
+        // Create egui-winit clipboard using the SAFE API
+        // Note: NO unsafe block here! This is the soundness bug.
+        let threads_before = App::thread_count();
+        let create = || egui_winit::clipboard::Clipboard::new(raw_display);
+        let clipboard = match self.config.pin_worker {
+            Some(core) => affinity::pinned(core, self.sink.as_mut(), create),
+            None => create(),
+        };
+        let threads_after = App::thread_count();
+        self.clipboard = Some(clipboard);
+        self.clipboard_created = true;
+        self.record(Event::ClipboardCreated);
+        self.emit(Diagnostic::info(
+            "clipboard-created",
+            "Created egui-winit Clipboard (safe API, no unsafe block!)",
+        ));
+
+        if self.config.count_threads {
+            if cfg!(target_os = "linux") {
+                let counts = ThreadCounts {
+                    before: threads_before,
+                    after: threads_after,
+                };
+                self.thread_counts = Some(counts);
+                let delta = counts.spawned();
+                self.emit(
+                    Diagnostic::info(
+                        "thread-count",
+                        format!(
+                            "Threads: {threads_before} before clipboard, {threads_after} after ({delta:+})"
+                        ),
+                    )
+                    .field("before", threads_before)
+                    .field("after", threads_after),
+                );
+            } else {
+                self.emit(Diagnostic::warn(
+                    "thread-count",
+                    "Thread counting unsupported on this platform",
+                ));
+            }
+        }
+    }
+
+    /// Create the clipboard on the event loop's display and keep it busy for
+    /// `hold`, all before any window exists.
+    fn hold(&mut self, event_loop: &ActiveEventLoop, hold: Duration) {
+        let raw_display = event_loop.display_handle().ok().map(|h| h.as_raw());
+        if !matches!(raw_display, Some(RawDisplayHandle::Wayland(_))) {
+            self.emit(Diagnostic::warn(
+                "hold-skipped",
+                "Not running on Wayland, ignoring --hold",
+            ));
+            return;
+        }
+
+        self.emit(Diagnostic::info(
+            "hold-start",
+            format!("Holding {hold:?} of clipboard traffic before creating the window"),
+        ));
+        self.create_clipboard(raw_display);
+
+        let start = Instant::now();
+        let mut ops = 0u64;
+        while start.elapsed() < hold {
+            self.clipboard_set(format!("hold {ops}"));
+            self.clipboard_get();
+            ops += 1;
+        }
+        self.emit(
+            Diagnostic::info(
+                "hold-done",
+                format!("Held for {hold:?}: {ops} set/get attempts"),
+            )
+            .field("ops", ops),
+        );
+    }
+
+    /// Whether [`WATCH_INTERVAL`] has passed since the clipboard was last read.
+    fn poll_due(&mut self) -> bool {
+        let due = self
+            .watch
+            .last_poll
+            .is_none_or(|last| last.elapsed() >= WATCH_INTERVAL);
+        if due {
+            self.watch.last_poll = Some(Instant::now());
+        }
+        due
+    }
+
+    /// Read the clipboard and log it if it changed since the last read.
+    fn watch_poll(&mut self, elapsed: Duration) {
+        let Some(text) = self.clipboard_get() else {
+            return;
+        };
+        // The first read is the starting point, not a change
+        let changed = self
+            .watch
+            .last_seen
+            .as_ref()
+            .is_some_and(|last| *last != text);
+        if changed {
+            let preview: String = text.chars().take(60).collect();
+            self.emit(
+                Diagnostic::info(
+                    "clipboard-changed",
+                    format!(
+                        "[{:.3}s] Clipboard changed: {preview:?}",
+                        elapsed.as_secs_f64()
+                    ),
+                )
+                .field("elapsed_ms", elapsed.as_millis() as u64)
+                .field("text", text.clone()),
+            );
+        }
+        self.watch.last_seen = Some(text);
+    }
+
+    /// Create a clipboard, round-trip some text through it and drop it.
+    fn burn_in_iteration(&mut self) {
+        let Some(window) = &self.window else {
+            return;
+        };
+        let raw_display = window.display_handle().ok().map(|h| h.as_raw());
+        let text = self.fit_write(format!("burn-in {}", self.burn_in.iterations));
+
+        let mut clipboard = egui_winit::clipboard::Clipboard::new(raw_display);
+        if let Some(text) = &text {
+            self.op_timings
+                .time(Op::Set, || clipboard.set_text(text.clone()));
+        }
+        let read_back = self.op_timings.time(Op::Get, || clipboard.get());
+        drop(clipboard);
+
+        self.burn_in.iterations += 1;
+        // A rejected write leaves nothing to read back, so it's never clean
+        if text.is_some() && read_back == text {
+            self.burn_in.clean += 1;
+        }
+    }
+
+    fn drop_window(&mut self) {
+        if self.window.take().is_none() {
+            self.emit(Diagnostic::warn("window-dropped", "Window already dropped"));
+            return;
+        }
+        let message = if self.clipboard.is_some() {
+            "Dropped window (D) while the clipboard is still alive"
+        } else {
+            "Dropped window (D)"
+        };
+        self.record(Event::WindowDropped);
+        self.emit(Diagnostic::info("window-dropped", message));
+    }
+
+    fn drop_clipboard(&mut self) {
+        if self.clipboard.take().is_none() {
+            self.emit(Diagnostic::warn(
+                "clipboard-dropped",
+                "Clipboard already dropped",
+            ));
+            return;
+        }
+        self.record(Event::ClipboardDropped);
+        self.emit(Diagnostic::info(
+            "clipboard-dropped",
+            "Dropped clipboard (C)",
+        ));
+    }
+
+    fn emit(&mut self, diagnostic: Diagnostic) {
+        self.sink.emit(diagnostic);
+    }
+
+    fn exit(&mut self, event_loop: &ActiveEventLoop, reason: ShutdownReason) {
+        self.emit(
+            Diagnostic::info("exit", "Exiting event loop").field("reason", format!("{reason:?}")),
+        );
+        self.record(Event::Exit);
+        self.shutdown_reason = Some(reason);
+        event_loop.exit();
+    }
+}
+
+impl ApplicationHandler for App {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        self.event_count += 1;
+        if self.window.is_none() {
+            if let Some(hold) = self.config.hold {
+                self.hold(event_loop, hold);
+            }
+
+            let title = format!("Will auto-close in {:?}", self.config.timeout);
+            let attributes = Window::default_attributes().with_title(title);
+            let window = match (self.create_window)(event_loop, attributes) {
+                Ok(window) => Arc::new(window),
+                Err(err) => {
+                    self.emit(Diagnostic::error(
+                        "window-failed",
+                        format!("Failed to create window: {err}"),
+                    ));
+                    self.exit(event_loop, ShutdownReason::Error);
+                    return;
+                }
+            };
+            self.record(Event::WindowCreated);
+            self.emit(Diagnostic::info("window-created", "Created window"));
+
+            // Get the display handle
+            let raw_display = window.display_handle().ok().map(|h| h.as_raw());
+
+            // When replaying, only create the clipboard if the recorded session did
+            let wants_clipboard = self
+                .replay
+                .as_ref()
+                .is_none_or(|replay| replay.contains(Event::ClipboardCreated));
+
+            // Check if we're on Wayland
+            if self.clipboard.is_some() {
+                // Already created by --hold
+            } else if !wants_clipboard {
+                self.emit(Diagnostic::info(
+                    "clipboard-skipped",
+                    "Recorded session had no clipboard, skipping",
+                ));
+            } else if let Some(RawDisplayHandle::Wayland(_)) = raw_display {
+                self.create_clipboard(raw_display);
+            } else {
+                self.emit(Diagnostic::info(
+                    "not-wayland",
+                    "Not running on Wayland, segfault won't occur",
+                ));
+            }
+
+            // Watching and waiting poll the clipboard, so without one they'd
+            // never finish
+            if (self.config.watch_external || self.config.wait_for_owner)
+                && self.clipboard.is_none()
+            {
+                self.emit(Diagnostic::error(
+                    "no-clipboard",
+                    "No clipboard to read, exiting",
+                ));
+                self.exit(event_loop, ShutdownReason::Error);
+                return;
+            }
+
+            // Start the frame loop; each redraw requests the next one
+            window.request_redraw();
+            self.window = Some(window);
+            self.start_time = Some(Instant::now());
+
+            // Request continuous polling so we can check the timer
+            event_loop.set_control_flow(ControlFlow::Poll);
+        }
+    }
+
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        self.event_count += 1;
+        let Some(start) = self.start_time else {
+            return;
+        };
+        if event_loop.exiting() {
+            return;
+        }
+
+        // A replayed session exits when the recording did, not on the timer
+        if let Some(replay) = &mut self.replay {
+            let elapsed = self.origin.elapsed();
+            let mut due = Vec::new();
+            while let Some(event) = replay.next_due(elapsed) {
+                due.push(event);
+            }
+            let finished = replay.is_empty();
+
+            let mut exit = false;
+            for event in due {
+                match event {
+                    Event::ClipboardSet => self.clipboard_set("replay".to_owned()),
+                    Event::ClipboardGet => {
+                        self.clipboard_get();
+                    }
+                    Event::WindowDropped => self.drop_window(),
+                    Event::ClipboardDropped => self.drop_clipboard(),
+                    Event::CloseRequested | Event::Exit => exit = true,
+                    // Already decided when the window was created
+                    Event::WindowCreated | Event::ClipboardCreated => {}
+                }
+            }
+            if exit {
+                self.emit(Diagnostic::info(
+                    "closing",
+                    format!("Replayed session exit, closing window...\n{WATCH_FOR_SEGFAULT}"),
+                ));
+                self.exit(event_loop, ShutdownReason::Replay);
+            } else if finished && start.elapsed() >= self.config.timeout {
+                // The recording never exited (it crashed), so fall back to the timer
+                self.emit(Diagnostic::info(
+                    "closing",
+                    format!(
+                        "Replay recorded no exit, auto-closing window after {:?}...\n{WATCH_FOR_SEGFAULT}",
+                        self.config.timeout
+                    ),
+                ));
+                self.exit(event_loop, ShutdownReason::Timeout);
+            }
+            return;
+        }
+
+        if let Some(duration) = self.config.burn_in {
+            self.burn_in_iteration();
+            let elapsed = start.elapsed();
+            // Report every second, so a crash still leaves the last rate behind
+            if elapsed.as_secs() > self.burn_in.reported {
+                self.burn_in.reported = elapsed.as_secs();
+                let (iterations, seconds) = (self.burn_in.iterations, self.burn_in.reported);
+                self.emit(
+                    Diagnostic::info(
+                        "burn-in-progress",
+                        format!(
+                            "Burn-in: {iterations} iterations in {seconds}s ({:.0}/s)",
+                            iterations as f64 / elapsed.as_secs_f64()
+                        ),
+                    )
+                    .field("iterations", iterations)
+                    .field("seconds", seconds),
+                );
+            }
+            if elapsed >= duration {
+                let BurnIn {
+                    iterations, clean, ..
+                } = self.burn_in;
+                self.emit(
+                    Diagnostic::info(
+                        "burn-in-finished",
+                        format!(
+                            "Burn-in finished: {iterations} iterations, {clean} clean, {:.0}/s",
+                            iterations as f64 / elapsed.as_secs_f64()
+                        ),
+                    )
+                    .field("iterations", iterations)
+                    .field("clean", clean),
+                );
+                self.exit(event_loop, ShutdownReason::BurnIn);
+            }
+            return;
+        }
+
+        if self.config.watch_external {
+            if self.watch.interrupted.load(Ordering::Relaxed) {
+                // Ordered shutdown: the clipboard goes while the display is alive
+                self.clipboard = None;
+                self.emit(Diagnostic::info(
+                    "interrupted",
+                    "Ctrl+C, dropped clipboard before closing window",
+                ));
+                self.exit(event_loop, ShutdownReason::Interrupted);
+            }
+            // The clipboard itself is polled once per frame, in RedrawRequested
+            return;
+        }
+
+        if self.config.wait_for_owner {
+            if self
+                .config
+                .wait_timeout
+                .is_some_and(|timeout| start.elapsed() >= timeout)
+            {
+                self.clipboard = None;
+                self.emit(Diagnostic::error(
+                    "wait-timeout",
+                    "Timed out waiting for clipboard content",
+                ));
+                self.exit(event_loop, ShutdownReason::WaitTimeout);
+            } else if self.poll_due() {
+                if let Some(text) = self.clipboard_get().filter(|text| !text.is_empty()) {
+                    self.clipboard = None;
+                    self.emit(Diagnostic::info("clipboard-content", text));
+                    self.exit(event_loop, ShutdownReason::ContentAvailable);
+                }
+            }
+            return;
+        }
+
+        // Auto-close once the timeout has elapsed
+        if start.elapsed() >= self.config.timeout {
+            let message = format!(
+                "Auto-closing window after {:?}...\n{WATCH_FOR_SEGFAULT}",
+                self.config.timeout
+            );
+            self.emit(Diagnostic::info("closing", message));
+            self.exit(event_loop, ShutdownReason::Timeout);
+        }
+    }
+
+    fn exiting(&mut self, _event_loop: &ActiveEventLoop) {
+        self.event_count += 1;
+        // The event loop, and with it the display, is still alive here, so
+        // this is the last point where the clipboard can be dropped safely
+        if self.config.ordered_exit && self.clipboard.take().is_some() {
+            self.emit(Diagnostic::info(
+                "clipboard-dropped",
+                "Dropped clipboard in exiting(), before the display",
+            ));
+        }
+    }
+
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, _id: WindowId, event: WindowEvent) {
+        self.event_count += 1;
+        match event {
+            WindowEvent::CloseRequested => {
+                self.emit(Diagnostic::info(
+                    "close-requested",
+                    "Window close requested, exiting...",
+                ));
+                self.record(Event::CloseRequested);
+                self.exit(event_loop, ShutdownReason::CloseRequested);
+            }
+            // `D` drops the window and `C` the clipboard, so either teardown
+            // order can be shown live
+            WindowEvent::KeyboardInput { event, .. }
+                if event.state == ElementState::Pressed && !event.repeat =>
+            {
+                match event.logical_key.as_ref() {
+                    Key::Character(c) if c.eq_ignore_ascii_case("d") => self.drop_window(),
+                    Key::Character(c) if c.eq_ignore_ascii_case("c") => self.drop_clipboard(),
+                    _ => {}
+                }
+            }
+            WindowEvent::RedrawRequested => {
+                if event_loop.exiting() {
+                    return;
+                }
+                self.frame_count += 1;
+                if self
+                    .config
+                    .frames
+                    .is_some_and(|limit| self.frame_count >= limit)
+                {
+                    let message = format!(
+                        "Closing window after {} frames...\n{WATCH_FOR_SEGFAULT}",
+                        self.frame_count
+                    );
+                    self.emit(Diagnostic::info("closing", message));
+                    self.exit(event_loop, ShutdownReason::FrameLimit);
+                    return;
+                }
+                if self.config.watch_external && self.poll_due() {
+                    if let Some(start) = self.start_time {
+                        self.watch_poll(start.elapsed());
+                    }
+                }
+                if let Some(window) = &self.window {
+                    window.request_redraw();
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Run the reproduction once, from creating the event loop to dropping the app.
+///
+/// On Wayland this usually doesn't return: dropping the app at the end is
+/// what segfaults. With [`ClipboardConfig::assert_no_leak`] set, a clipboard
+/// that outlived the event loop is reported as an error and the app is
+/// leaked instead of dropped, so this returns with
+/// [`RunOutcome::clipboard_alive`] set.
+///
+/// With [`ClipboardConfig::dry_run`] set, it prints the resolved
+/// configuration and returns an empty outcome instead of running.
+pub fn run_once(config: ClipboardConfig) -> io::Result<RunOutcome> {
+    run_with(config, create_window)
+}
+
+/// [`run_once`], creating the window with `create_window`.
+pub fn run_with(
+    mut config: ClipboardConfig,
+    create_window: WindowFactory,
+) -> io::Result<RunOutcome> {
+    let replay = config.replay.as_deref().map(Replay::load).transpose()?;
+
+    let mut sink = config.sink();
+    if let Some((tool, factor)) = timeout_scale() {
+        config.timeout = effective_timeout(config.timeout);
+        config.wait_timeout = config.wait_timeout.map(effective_timeout);
+        sink.emit(
+            Diagnostic::info(
+                "timeout-scaled",
+                format!("Running under {tool}, scaling timeouts by {factor}x"),
+            )
+            .field("factor", u64::from(factor)),
+        );
+    }
+    let starting = if config.watch_external {
+        "Window stays open until Ctrl+C...".to_owned()
+    } else if config.wait_for_owner {
+        "Waiting for clipboard content...".to_owned()
+    } else {
+        format!("Window will auto-close in {:?}...", config.timeout)
+    };
+
+    if config.dry_run {
+        sink.emit(plan(&config, &starting));
+        sink.finish();
+        return Ok(RunOutcome {
+            event_count: 0,
+            frame_count: 0,
+            shutdown_reason: None,
+            clipboard_created: false,
+            clipboard_alive: false,
+            crashed_early: false,
+            thread_counts: None,
+            op_timings: OpTimings::default(),
+        });
+    }
+    sink.emit(Diagnostic::info("starting", starting));
+
+    let recorder = match config.record.as_deref().map(Recorder::create).transpose() {
+        Ok(recorder) => recorder,
+        Err(err) => {
+            sink.finish();
+            return Err(err);
+        }
+    };
+    let event_loop = match EventLoop::new() {
+        Ok(event_loop) => event_loop,
+        Err(err) => {
+            sink.finish();
+            return Err(io::Error::other(err));
+        }
+    };
+    let mut app = App {
+        config,
+        sink,
+        create_window,
+        window: None,
+        clipboard: None,
+        clipboard_created: false,
+        start_time: None,
+        origin: Instant::now(),
+        recorder,
+        replay,
+        event_count: 0,
+        frame_count: 0,
+        shutdown_reason: None,
+        burn_in: BurnIn::default(),
+        watch: Watch::default(),
+        op_timings: OpTimings::default(),
+        thread_counts: None,
+        oversize_writes: 0,
+        op_traces: Default::default(),
+    };
+
+    if app.config.watch_external {
+        let interrupted = app.watch.interrupted.clone();
+        match ctrlc::set_handler(move || interrupted.store(true, Ordering::Relaxed)) {
+            Ok(()) => app.emit(Diagnostic::info(
+                "watching",
+                "Watching the clipboard for external changes, Ctrl+C to stop",
+            )),
+            Err(err) => app.emit(Diagnostic::warn(
+                "watching",
+                format!("Can't handle Ctrl+C ({err}), close the window to stop"),
+            )),
+        }
+    }
+
+    if let Err(err) = event_loop.run_app(&mut app) {
+        app.sink.finish();
+        return Err(io::Error::other(err));
+    }
+
+    let outcome = RunOutcome {
+        event_count: app.event_count,
+        frame_count: app.frame_count,
+        shutdown_reason: app.shutdown_reason,
+        clipboard_created: app.clipboard_created,
+        clipboard_alive: app.clipboard.is_some(),
+        crashed_early: crashed_early(app.event_count, app.shutdown_reason, app.config.timeout),
+        thread_counts: app.thread_counts,
+        op_timings: std::mem::take(&mut app.op_timings),
+    };
+
+    app.emit(
+        Diagnostic::info(
+            "outcome",
+            format!(
+                "Shut down ({:?}) after {} events, {} frames",
+                outcome.shutdown_reason, outcome.event_count, outcome.frame_count
+            ),
+        )
+        .field("event_count", outcome.event_count)
+        .field("frame_count", outcome.frame_count)
+        .field("shutdown_reason", format!("{:?}", outcome.shutdown_reason))
+        .field("clipboard_created", outcome.clipboard_created)
+        .field("clipboard_alive", outcome.clipboard_alive)
+        .field("crashed_early", outcome.crashed_early)
+        .field("oversize_writes", app.oversize_writes),
+    );
+    for op in [Op::Set, Op::Get] {
+        if let Some(stats) = outcome.op_timings.stats(op) {
+            app.emit(
+                Diagnostic::info(
+                    "op-timing",
+                    format!(
+                        "{} {} ops: min {:?}, max {:?}, avg {:?}",
+                        stats.count,
+                        op.name(),
+                        stats.min,
+                        stats.max,
+                        stats.avg
+                    ),
+                )
+                .field("op", op.name())
+                .field("count", stats.count)
+                .field("min_us", stats.min.as_micros() as u64)
+                .field("max_us", stats.max.as_micros() as u64)
+                .field("avg_us", stats.avg.as_micros() as u64),
+            );
+        }
+    }
+    if outcome.crashed_early {
+        app.emit(Diagnostic::warn(
+            "crashed-early",
+            "Event loop stopped before doing anything; did the window ever map?",
+        ));
+    }
+
+    // Check and flush output before dropping the app, since the drop is what crashes
+    if app.config.assert_no_leak && outcome.clipboard_alive {
+        app.emit(Diagnostic::error(
+            "leak",
+            "Clipboard (and its smithay thread) still alive after the event loop exited",
+        ));
+        app.sink.finish();
+        // Dropping it now would segfault, taking the caller down with it
+        std::mem::forget(app);
+        return Ok(outcome);
+    }
+
+    app.emit(Diagnostic::info(
+        "dropping",
+        "Event loop exited, dropping App...\n\
+         (window drops first, then clipboard tries to use invalid display)",
+    ));
+    app.sink.finish();
+
+    Ok(outcome)
+}
+
+/// The `--dry-run` report: what `run_once` would do with `config`.
+fn plan(config: &ClipboardConfig, starting: &str) -> Diagnostic {
+    let mut plan = Diagnostic::info(
+        "plan",
+        format!("Dry run, not creating a window or clipboard. {starting}"),
+    )
+    .show_fields()
+    .field("format", format!("{:?}", config.format))
+    .field("trace_stdout", config.trace_stdout)
+    .field("color", format!("{:?}", config.color))
+    .field("timeout_ms", config.timeout.as_millis() as u64)
+    .field("watch_external", config.watch_external)
+    .field("wait_for_owner", config.wait_for_owner)
+    .field("count_threads", config.count_threads)
+    .field("ordered_exit", config.ordered_exit)
+    .field("assert_no_leak", config.assert_no_leak)
+    .field("expect_crash", config.expect_crash);
+    if let Some(frames) = config.frames {
+        plan = plan.field("frames", frames);
+    }
+    if let Some(burn_in) = config.burn_in {
+        plan = plan.field("burn_in_s", burn_in.as_secs());
+    }
+    if let Some(wait_timeout) = config.wait_timeout {
+        plan = plan.field("wait_timeout_ms", wait_timeout.as_millis() as u64);
+    }
+    if let Some(hold) = config.hold {
+        plan = plan.field("hold_ms", hold.as_millis() as u64);
+    }
+    if let Some(max_bytes) = config.max_bytes {
+        plan = plan
+            .field("max_bytes", max_bytes)
+            .field("on_oversize", format!("{:?}", config.on_oversize));
+    }
+    if let Some(core) = config.pin_worker {
+        plan = plan.field("pin_worker", core);
+    }
+    if let Some(path) = &config.record {
+        plan = plan.field("record", path.display().to_string());
+    }
+    if let Some(path) = &config.replay {
+        plan = plan.field("replay", path.display().to_string());
+    }
+    plan
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::Value;
+
+    const TIMEOUT: Duration = Duration::from_secs(1);
+
+    #[test]
+    fn op_trace_is_rate_limited() {
+        let start = Instant::now();
+        let mut trace = OpTrace::default();
+        assert_eq!(trace.due(start), Some(0));
+        assert_eq!(trace.due(start + OP_TRACE_INTERVAL / 2), None);
+        assert_eq!(trace.due(start + OP_TRACE_INTERVAL / 2), None);
+        assert_eq!(trace.due(start + OP_TRACE_INTERVAL), Some(2));
+        assert_eq!(trace.due(start + OP_TRACE_INTERVAL * 3), Some(0));
+    }
+
+    #[test]
+    fn early_unrequested_stops_are_flagged() {
+        for reason in [
+            None,
+            Some(ShutdownReason::Timeout),
+            Some(ShutdownReason::Error),
+        ] {
+            assert!(crashed_early(3, reason, TIMEOUT), "{reason:?}");
+        }
+    }
+
+    #[test]
+    fn requested_stops_are_never_flagged() {
+        for reason in [
+            ShutdownReason::CloseRequested,
+            ShutdownReason::Interrupted,
+            ShutdownReason::FrameLimit,
+        ] {
+            assert!(!crashed_early(0, Some(reason), TIMEOUT), "{reason:?}");
+        }
+    }
+
+    fn plan_field(plan: &Diagnostic, name: &str) -> Option<Value> {
+        plan.fields
+            .iter()
+            .find(|(field, _)| *field == name)
+            .map(|(_, value)| value.clone())
+    }
+
+    #[test]
+    fn dry_run_does_nothing() {
+        let record = std::env::temp_dir().join(format!(
+            "smithay-clipboard-segfault-{}-dry-run",
+            std::process::id()
+        ));
+        let outcome = run_once(ClipboardConfig {
+            dry_run: true,
+            record: Some(record.clone()),
+            ..ClipboardConfig::default()
+        })
+        .unwrap();
+        assert_eq!(outcome.event_count, 0);
+        assert_eq!(outcome.frame_count, 0);
+        assert!(!outcome.clipboard_created);
+        assert_eq!(outcome.shutdown_reason, None);
+        assert!(!outcome.clipboard_alive);
+        assert_eq!(outcome.thread_counts, None);
+        assert_eq!(outcome.op_timings().stats(Op::Get), None);
+        assert!(!record.exists(), "dry run created {}", record.display());
+    }
+
+    #[test]
+    fn plan_lists_the_resolved_config() {
+        let config = ClipboardConfig {
+            timeout: Duration::from_millis(300),
+            frames: Some(3),
+            ordered_exit: true,
+            ..ClipboardConfig::default()
+        };
+        let plan = plan(&config, "Window will auto-close in 300ms...");
+        assert_eq!(plan.event, "plan");
+        assert_eq!(
+            plan.message,
+            "Dry run, not creating a window or clipboard. Window will auto-close in 300ms..."
+        );
+        assert!(plan.show_fields);
+        assert_eq!(plan_field(&plan, "timeout_ms"), Some(Value::Int(300)));
+        assert_eq!(plan_field(&plan, "frames"), Some(Value::Int(3)));
+        assert_eq!(plan_field(&plan, "ordered_exit"), Some(Value::Bool(true)));
+        assert_eq!(plan_field(&plan, "format"), Some(Value::from("Text")));
+        // Unset options are left out rather than reported as empty
+        assert_eq!(plan_field(&plan, "hold_ms"), None);
+        assert_eq!(plan_field(&plan, "record"), None);
+    }
+
+    #[test]
+    fn enough_events_are_not_flagged() {
+        // One second at one event per 10ms
+        assert!(!crashed_early(100, Some(ShutdownReason::Timeout), TIMEOUT));
+        assert!(crashed_early(99, Some(ShutdownReason::Timeout), TIMEOUT));
+    }
+}
@@ -0,0 +1,100 @@
+//! ANSI coloring for the text and trace output, selected with `--color`.
+//!
+//! Errors are red, warnings yellow, and the events that make up the teardown
+//! order (the window, the clipboard and the app being dropped) bold cyan, so
+//! the dangerous sequence stands out while debugging.
+
+use std::io::IsTerminal;
+use std::str::FromStr;
+
+use crate::diagnostics::{Diagnostic, Level};
+
+/// Events in the teardown sequence that causes the crash.
+const TEARDOWN_EVENTS: &[&str] = &["exit", "window-dropped", "clipboard-dropped", "dropping"];
+
+/// The `--color` choices.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorChoice {
+    /// Color if the stream is a terminal and `NO_COLOR` isn't set.
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    /// Whether output written to `stream` should be colored.
+    pub fn enabled(self, stream: &impl IsTerminal) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => {
+                std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+                    && stream.is_terminal()
+            }
+        }
+    }
+}
+
+impl FromStr for ColorChoice {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "auto" => Ok(ColorChoice::Auto),
+            "always" => Ok(ColorChoice::Always),
+            "never" => Ok(ColorChoice::Never),
+            _ => Err(format!("unknown color choice {s:?}")),
+        }
+    }
+}
+
+/// Wrap `text` in the color for `diagnostic`, if it has one.
+pub(crate) fn paint(diagnostic: &Diagnostic, text: &str) -> String {
+    let code = match diagnostic.level {
+        Level::Error => "31",
+        Level::Warn => "33",
+        Level::Info if TEARDOWN_EVENTS.contains(&diagnostic.event) => "1;36",
+        Level::Info => return text.to_owned(),
+    };
+    format!("\x1b[{code}m{text}\x1b[0m")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ClipboardConfig;
+
+    #[test]
+    fn never_and_always_ignore_the_stream() {
+        assert!(!ColorChoice::Never.enabled(&std::io::stdout()));
+        assert!(ColorChoice::Always.enabled(&std::io::stdout()));
+    }
+
+    #[test]
+    fn no_color_flag_is_never() {
+        let config = ClipboardConfig::from_args(["--no-color".to_owned()]).unwrap();
+        assert_eq!(config.color, ColorChoice::Never);
+        let config =
+            ClipboardConfig::from_args(["--color".to_owned(), "always".to_owned()]).unwrap();
+        assert_eq!(config.color, ColorChoice::Always);
+    }
+
+    #[test]
+    fn errors_are_red() {
+        let diagnostic = Diagnostic::error("window-failed", "");
+        assert_eq!(paint(&diagnostic, "boom"), "\x1b[31mboom\x1b[0m");
+    }
+
+    #[test]
+    fn teardown_events_are_bold_cyan() {
+        let diagnostic = Diagnostic::info("dropping", "");
+        assert_eq!(paint(&diagnostic, "bye"), "\x1b[1;36mbye\x1b[0m");
+    }
+
+    #[test]
+    fn other_info_is_plain() {
+        let diagnostic = Diagnostic::info("window-created", "");
+        assert_eq!(paint(&diagnostic, "hi"), "hi");
+    }
+}
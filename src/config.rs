@@ -0,0 +1,420 @@
+//! Command line configuration for a reproduction run.
+
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use crate::color::ColorChoice;
+use crate::diagnostics::{DiagnosticSink, Format, TextSink};
+use crate::trace::TraceSink;
+use std::time::Duration;
+
+pub const USAGE: &str = "\
+Usage: smithay-clipboard-segfault [options]
+
+  --format <format>      Diagnostic output: text, json or ndjson (default text)
+  --trace-stdout         Print a timestamped, aligned event trace instead
+  --color <when>         Color text and trace output: auto, always or never
+  --no-color             Same as --color never
+  --timeout <ms>         Close the window after this long (default 1000)
+  --frames <n>           Close the window after n frames
+  --burn-in <seconds>    Create, use and drop clipboards in a loop this long
+  --watch-external       Log external clipboard changes until Ctrl+C
+  --wait-for-owner       Wait until the clipboard has content, print it, exit
+  --wait-timeout <ms>    Give up waiting after this long and exit 1
+  --hold <ms>            Use the clipboard this long before creating the window
+  --max-bytes <n>        Limit clipboard writes to n bytes
+  --on-oversize <policy> What to do with larger writes: truncate or reject
+  --count-threads        Log threads spawned by creating the clipboard
+  --pin-worker <core>    Pin the clipboard's thread to a CPU core (Linux)
+  --record <path>        Write lifecycle events to a file
+  --replay <path>        Re-run lifecycle events from a recorded file
+  --ordered-exit         Drop the clipboard in exiting(), before the display
+  --assert-no-leak       Exit 1 if the clipboard outlives the event loop
+  --expect-crash         Re-run in a child and exit 0 only if it segfaults
+  --dry-run              Print the resolved configuration and exit";
+
+/// Everything that controls a single [`run_once`](crate::run_once).
+#[derive(Debug, Clone)]
+pub struct ClipboardConfig {
+    /// How diagnostics are written.
+    pub format: Format,
+    /// Print a compact event trace instead of `format`.
+    pub trace_stdout: bool,
+    /// Whether text and trace output is colored.
+    pub color: ColorChoice,
+    /// How long the window stays open before closing itself.
+    pub timeout: Duration,
+    /// Close the window after this many frames have been drawn.
+    pub frames: Option<u64>,
+    /// Instead of waiting for the timeout, repeatedly create, use and drop
+    /// clipboards for this long.
+    pub burn_in: Option<Duration>,
+    /// Keep the window open and log whenever the clipboard changes, until
+    /// Ctrl+C.
+    pub watch_external: bool,
+    /// Wait until the clipboard has text, print it and exit.
+    pub wait_for_owner: bool,
+    /// How long `wait_for_owner` waits before giving up.
+    pub wait_timeout: Option<Duration>,
+    /// Create the clipboard and keep it busy this long before the window
+    /// exists.
+    pub hold: Option<Duration>,
+    /// The largest write the app makes to the clipboard, in bytes.
+    pub max_bytes: Option<usize>,
+    /// What happens to writes over `max_bytes`.
+    pub on_oversize: Oversize,
+    /// Log how many threads creating the clipboard spawned.
+    pub count_threads: bool,
+    /// Pin the thread smithay-clipboard spawns to this CPU core.
+    pub pin_worker: Option<usize>,
+    /// Write lifecycle events to this file.
+    pub record: Option<PathBuf>,
+    /// Re-run the lifecycle events recorded in this file.
+    pub replay: Option<PathBuf>,
+    /// Drop the clipboard in `ApplicationHandler::exiting`, while the display
+    /// is still alive, instead of leaving it to the app's drop.
+    pub ordered_exit: bool,
+    /// Report an error and leak the app instead of dropping it if the
+    /// clipboard is still alive once the event loop has exited. The binary
+    /// then exits with status 1.
+    pub assert_no_leak: bool,
+    /// Run the reproduction in a child process and succeed only if it
+    /// crashed with SIGSEGV.
+    pub expect_crash: bool,
+    /// Print the resolved configuration and return without creating a
+    /// window or clipboard.
+    pub dry_run: bool,
+}
+
+impl Default for ClipboardConfig {
+    fn default() -> Self {
+        Self {
+            format: Format::Text,
+            trace_stdout: false,
+            color: ColorChoice::Auto,
+            timeout: Duration::from_secs(1),
+            frames: None,
+            burn_in: None,
+            watch_external: false,
+            wait_for_owner: false,
+            wait_timeout: None,
+            hold: None,
+            max_bytes: None,
+            on_oversize: Oversize::Truncate,
+            count_threads: false,
+            pin_worker: None,
+            record: None,
+            replay: None,
+            ordered_exit: false,
+            assert_no_leak: false,
+            expect_crash: false,
+            dry_run: false,
+        }
+    }
+}
+
+impl ClipboardConfig {
+    /// Parse command line arguments, not including the program name.
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut config = Self::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--format" => config.format = value(&mut args, &arg)?,
+                "--trace-stdout" => config.trace_stdout = true,
+                "--color" => config.color = value(&mut args, &arg)?,
+                "--no-color" => config.color = ColorChoice::Never,
+                "--timeout" => config.timeout = Duration::from_millis(value(&mut args, &arg)?),
+                "--frames" => config.frames = Some(value(&mut args, &arg)?),
+                "--burn-in" => config.burn_in = Some(Duration::from_secs(value(&mut args, &arg)?)),
+                "--watch-external" => config.watch_external = true,
+                "--wait-for-owner" => config.wait_for_owner = true,
+                "--wait-timeout" => {
+                    config.wait_timeout = Some(Duration::from_millis(value(&mut args, &arg)?))
+                }
+                "--hold" => config.hold = Some(Duration::from_millis(value(&mut args, &arg)?)),
+                "--max-bytes" => config.max_bytes = Some(value(&mut args, &arg)?),
+                "--on-oversize" => config.on_oversize = value(&mut args, &arg)?,
+                "--count-threads" => config.count_threads = true,
+                "--pin-worker" => config.pin_worker = Some(value(&mut args, &arg)?),
+                "--record" => config.record = Some(value(&mut args, &arg)?),
+                "--replay" => config.replay = Some(value(&mut args, &arg)?),
+                "--ordered-exit" => config.ordered_exit = true,
+                "--assert-no-leak" => config.assert_no_leak = true,
+                "--expect-crash" => config.expect_crash = true,
+                "--dry-run" => config.dry_run = true,
+                other => return Err(format!("Unknown argument {other:?}")),
+            }
+        }
+        config.check_modes()?;
+        Ok(config)
+    }
+
+    /// Reject flag combinations where one mode would silently win, since
+    /// the event loop only runs one of them.
+    fn check_modes(&self) -> Result<(), String> {
+        let watch = ("--watch-external", self.watch_external);
+        let wait = ("--wait-for-owner", self.wait_for_owner);
+        let others = [
+            ("--burn-in", self.burn_in.is_some()),
+            ("--replay", self.replay.is_some()),
+            watch,
+        ];
+        for (mode, on) in [watch, wait] {
+            for (other, other_on) in others {
+                if on && other_on && other != mode {
+                    return Err(format!("{other} can't be combined with {mode}"));
+                }
+            }
+        }
+        if self.wait_timeout.is_some() && !self.wait_for_owner {
+            return Err("--wait-timeout needs --wait-for-owner".to_owned());
+        }
+        Ok(())
+    }
+
+    /// The sink diagnostics should go to.
+    pub fn sink(&self) -> Box<dyn DiagnosticSink> {
+        if self.trace_stdout {
+            Box::new(TraceSink::new(self.color))
+        } else if self.wait_for_owner && self.format == Format::Text {
+            // Scripts capture stdout, so that's reserved for the content
+            Box::new(TextSink::new(self.color).stdout_only("clipboard-content"))
+        } else {
+            self.format.sink(self.color)
+        }
+    }
+}
+
+/// The `--on-oversize` choices.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Oversize {
+    /// Write as much of the text as fits.
+    #[default]
+    Truncate,
+    /// Skip the write.
+    Reject,
+}
+
+impl Oversize {
+    /// What to write for `text` under a `max_bytes` limit: `text` itself if
+    /// it fits, otherwise the longest prefix that fits and ends on a char
+    /// boundary, or `None` to skip the write.
+    pub fn fit(self, mut text: String, max_bytes: usize) -> Option<String> {
+        if text.len() <= max_bytes {
+            return Some(text);
+        }
+        match self {
+            Oversize::Truncate => {
+                let mut end = max_bytes;
+                while !text.is_char_boundary(end) {
+                    end -= 1;
+                }
+                text.truncate(end);
+                Some(text)
+            }
+            Oversize::Reject => None,
+        }
+    }
+}
+
+impl FromStr for Oversize {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "truncate" => Ok(Oversize::Truncate),
+            "reject" => Ok(Oversize::Reject),
+            _ => Err(format!("unknown oversize policy {s:?}")),
+        }
+    }
+}
+
+/// The memory tool this is running under and how much to scale timeouts by.
+///
+/// Valgrind and the sanitizers slow the program down enough that a short
+/// timeout can fire before the window is even mapped, so the crash never
+/// gets a chance to happen. Valgrind is detected through
+/// `RUNNING_ON_VALGRIND` or its `vgpreload` libraries in `LD_PRELOAD`, and
+/// sanitizers through their `*SAN_OPTIONS` variables.
+///
+/// The factor applies to the deadlines a run gives up at: `--timeout` and
+/// `--wait-timeout`. `--hold` and `--burn-in` aren't deadlines but how long
+/// to keep the clipboard busy, and a slowed-down run just fits fewer
+/// operations into them, so they are left alone.
+pub fn timeout_scale() -> Option<(&'static str, u32)> {
+    if std::env::var_os("RUNNING_ON_VALGRIND").is_some()
+        || std::env::var("LD_PRELOAD").is_ok_and(|preload| preload.contains("vgpreload"))
+    {
+        Some(("Valgrind", 20))
+    } else if [
+        "ASAN_OPTIONS",
+        "MSAN_OPTIONS",
+        "TSAN_OPTIONS",
+        "LSAN_OPTIONS",
+    ]
+    .iter()
+    .any(|name| std::env::var_os(name).is_some())
+    {
+        Some(("a sanitizer", 4))
+    } else {
+        None
+    }
+}
+
+/// Scale `base` up when running under a memory tool, per [`timeout_scale`].
+///
+/// This is the one place the factor is applied.
+pub fn effective_timeout(base: Duration) -> Duration {
+    match timeout_scale() {
+        Some((_, factor)) => base * factor,
+        None => base,
+    }
+}
+
+fn value<T: FromStr>(args: &mut impl Iterator<Item = String>, flag: &str) -> Result<T, String> {
+    let value = args.next().ok_or_else(|| format!("{flag} needs a value"))?;
+    value
+        .parse()
+        .map_err(|_| format!("Invalid value {value:?} for {flag}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<ClipboardConfig, String> {
+        ClipboardConfig::from_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn no_args_is_the_default() {
+        let config = parse(&[]).unwrap();
+        assert_eq!(config.timeout, Duration::from_secs(1));
+        assert_eq!(config.format, Format::Text);
+        assert!(!config.dry_run);
+    }
+
+    #[test]
+    fn flags_and_values() {
+        let config = parse(&[
+            "--dry-run",
+            "--format",
+            "ndjson",
+            "--timeout",
+            "250",
+            "--frames",
+            "5",
+            "--burn-in",
+            "2",
+            "--record",
+            "session.log",
+            "--max-bytes",
+            "64",
+            "--on-oversize",
+            "reject",
+            "--ordered-exit",
+        ])
+        .unwrap();
+        assert!(config.dry_run);
+        assert_eq!(config.format, Format::Ndjson);
+        assert_eq!(config.timeout, Duration::from_millis(250));
+        assert_eq!(config.frames, Some(5));
+        assert_eq!(config.burn_in, Some(Duration::from_secs(2)));
+        assert_eq!(config.record, Some(PathBuf::from("session.log")));
+        assert_eq!(config.max_bytes, Some(64));
+        assert_eq!(config.on_oversize, Oversize::Reject);
+        assert!(config.ordered_exit);
+    }
+
+    #[test]
+    fn unknown_flag() {
+        assert_eq!(
+            parse(&["--bogus"]).unwrap_err(),
+            r#"Unknown argument "--bogus""#
+        );
+    }
+
+    #[test]
+    fn missing_value() {
+        assert_eq!(
+            parse(&["--timeout"]).unwrap_err(),
+            "--timeout needs a value"
+        );
+    }
+
+    #[test]
+    fn conflicting_modes() {
+        for (args, error) in [
+            (
+                &["--watch-external", "--wait-for-owner"][..],
+                "--watch-external can't be combined with --wait-for-owner",
+            ),
+            (
+                &["--burn-in", "1", "--watch-external"],
+                "--burn-in can't be combined with --watch-external",
+            ),
+            (
+                &["--wait-for-owner", "--burn-in", "1"],
+                "--burn-in can't be combined with --wait-for-owner",
+            ),
+            (
+                &["--replay", "session.log", "--watch-external"],
+                "--replay can't be combined with --watch-external",
+            ),
+            (
+                &["--replay", "session.log", "--wait-for-owner"],
+                "--replay can't be combined with --wait-for-owner",
+            ),
+        ] {
+            assert_eq!(parse(args).unwrap_err(), error, "{args:?}");
+        }
+    }
+
+    #[test]
+    fn wait_timeout_needs_wait_for_owner() {
+        assert_eq!(
+            parse(&["--wait-timeout", "200"]).unwrap_err(),
+            "--wait-timeout needs --wait-for-owner"
+        );
+        let config = parse(&["--wait-for-owner", "--wait-timeout", "200"]).unwrap();
+        assert_eq!(config.wait_timeout, Some(Duration::from_millis(200)));
+    }
+
+    #[test]
+    fn invalid_value() {
+        assert_eq!(
+            parse(&["--frames", "many"]).unwrap_err(),
+            r#"Invalid value "many" for --frames"#
+        );
+    }
+
+    #[test]
+    fn fits_at_the_limit() {
+        for policy in [Oversize::Truncate, Oversize::Reject] {
+            assert_eq!(policy.fit("abcd".to_owned(), 4).as_deref(), Some("abcd"));
+        }
+    }
+
+    #[test]
+    fn truncates_past_the_limit() {
+        assert_eq!(
+            Oversize::Truncate.fit("abcde".to_owned(), 4).as_deref(),
+            Some("abcd")
+        );
+    }
+
+    #[test]
+    fn truncates_on_a_char_boundary() {
+        // 'é' is two bytes, so cutting at 2 would split it
+        assert_eq!(
+            Oversize::Truncate.fit("aé".to_owned(), 2).as_deref(),
+            Some("a")
+        );
+    }
+
+    #[test]
+    fn rejects_past_the_limit() {
+        assert_eq!(Oversize::Reject.fit("abcde".to_owned(), 4), None);
+    }
+}
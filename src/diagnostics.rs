@@ -0,0 +1,452 @@
+//! Structured diagnostic output, selected with `--format`.
+//!
+//! Every lifecycle event goes through a [`DiagnosticSink`] as a
+//! [`Diagnostic`], which renders it as plain text, as part of a single JSON
+//! object, or as one JSON object per line (NDJSON) for log aggregators.
+
+use std::fmt::{self, Write as _};
+use std::io::{self, IsTerminal, Write};
+use std::str::FromStr;
+
+use crate::color::{self, ColorChoice};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Info,
+    Warn,
+    Error,
+}
+
+impl Level {
+    fn name(self) -> &'static str {
+        match self {
+            Level::Info => "info",
+            Level::Warn => "warn",
+            Level::Error => "error",
+        }
+    }
+}
+
+/// A field value attached to a [`Diagnostic`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Bool(bool),
+    Int(i64),
+    Str(String),
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Bool(value) => value.fmt(f),
+            Value::Int(value) => value.fmt(f),
+            Value::Str(value) => value.fmt(f),
+        }
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::Bool(value)
+    }
+}
+
+impl From<u64> for Value {
+    fn from(value: u64) -> Self {
+        Value::Int(value.try_into().unwrap_or(i64::MAX))
+    }
+}
+
+impl From<usize> for Value {
+    fn from(value: usize) -> Self {
+        Value::Int(value.try_into().unwrap_or(i64::MAX))
+    }
+}
+
+impl From<i64> for Value {
+    fn from(value: i64) -> Self {
+        Value::Int(value)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::Str(value.to_owned())
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::Str(value)
+    }
+}
+
+/// One event: a machine-readable name, a human-readable message and any
+/// extra fields.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub level: Level,
+    pub event: &'static str,
+    pub message: String,
+    pub fields: Vec<(&'static str, Value)>,
+    /// Whether text output lists `fields` under the message. They are
+    /// always part of the JSON.
+    pub show_fields: bool,
+}
+
+impl Diagnostic {
+    pub fn new(level: Level, event: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            level,
+            event,
+            message: message.into(),
+            fields: Vec::new(),
+            show_fields: false,
+        }
+    }
+
+    pub fn info(event: &'static str, message: impl Into<String>) -> Self {
+        Self::new(Level::Info, event, message)
+    }
+
+    pub fn warn(event: &'static str, message: impl Into<String>) -> Self {
+        Self::new(Level::Warn, event, message)
+    }
+
+    pub fn error(event: &'static str, message: impl Into<String>) -> Self {
+        Self::new(Level::Error, event, message)
+    }
+
+    pub fn field(mut self, name: &'static str, value: impl Into<Value>) -> Self {
+        self.fields.push((name, value.into()));
+        self
+    }
+
+    /// List the fields as `name: value` lines in text output.
+    pub fn show_fields(mut self) -> Self {
+        self.show_fields = true;
+        self
+    }
+
+    /// The message, followed by the fields if they're shown.
+    fn text(&self) -> String {
+        let mut text = self.message.clone();
+        if self.show_fields {
+            for (name, value) in &self.fields {
+                write!(text, "\n  {name}: {value}").unwrap();
+            }
+        }
+        text
+    }
+
+    /// Render as a single-line JSON object. Fields are flattened next to
+    /// `level`, `event` and `message`.
+    pub fn to_json(&self) -> String {
+        let mut json = String::from("{\"level\":");
+        write_json_str(&mut json, self.level.name());
+        json.push_str(",\"event\":");
+        write_json_str(&mut json, self.event);
+        json.push_str(",\"message\":");
+        write_json_str(&mut json, &self.message);
+        for (name, value) in &self.fields {
+            json.push(',');
+            write_json_str(&mut json, name);
+            json.push(':');
+            match value {
+                Value::Bool(value) => write!(json, "{value}").unwrap(),
+                Value::Int(value) => write!(json, "{value}").unwrap(),
+                Value::Str(value) => write_json_str(&mut json, value),
+            }
+        }
+        json.push('}');
+        json
+    }
+}
+
+fn write_json_str(out: &mut String, value: &str) {
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// Where diagnostics go.
+pub trait DiagnosticSink {
+    fn emit(&mut self, diagnostic: Diagnostic);
+
+    /// Called once all diagnostics have been emitted, before the app (and
+    /// with it the clipboard) is dropped, so output survives the crash.
+    fn finish(&mut self) {}
+}
+
+/// Plain messages, warnings and errors on stderr.
+#[derive(Default)]
+pub struct TextSink {
+    color: ColorChoice,
+    stdout_event: Option<&'static str>,
+}
+
+impl TextSink {
+    pub fn new(color: ColorChoice) -> Self {
+        Self {
+            color,
+            stdout_event: None,
+        }
+    }
+
+    /// Print only diagnostics named `event` on stdout and everything else on
+    /// stderr, so a script can capture just that output.
+    pub fn stdout_only(mut self, event: &'static str) -> Self {
+        self.stdout_event = Some(event);
+        self
+    }
+
+    fn render(&self, diagnostic: &Diagnostic, stream: &impl IsTerminal) -> String {
+        let text = diagnostic.text();
+        if self.color.enabled(stream) {
+            color::paint(diagnostic, &text)
+        } else {
+            text
+        }
+    }
+}
+
+impl DiagnosticSink for TextSink {
+    fn emit(&mut self, diagnostic: Diagnostic) {
+        let to_stdout = match self.stdout_event {
+            Some(event) => diagnostic.event == event,
+            None => diagnostic.level == Level::Info,
+        };
+        if to_stdout {
+            println!("{}", self.render(&diagnostic, &io::stdout()));
+        } else {
+            eprintln!("{}", self.render(&diagnostic, &io::stderr()));
+        }
+    }
+}
+
+/// Collects everything into one `{"events": [...]}` object, written to
+/// stdout (or `out`) on finish.
+pub struct JsonSink<W = io::Stdout> {
+    out: W,
+    events: Vec<String>,
+}
+
+impl<W: Write> JsonSink<W> {
+    pub fn new(out: W) -> Self {
+        Self {
+            out,
+            events: Vec::new(),
+        }
+    }
+}
+
+impl Default for JsonSink {
+    fn default() -> Self {
+        Self::new(io::stdout())
+    }
+}
+
+impl<W: Write> DiagnosticSink for JsonSink<W> {
+    fn emit(&mut self, diagnostic: Diagnostic) {
+        self.events.push(diagnostic.to_json());
+    }
+
+    fn finish(&mut self) {
+        // Nowhere left to report a failed write
+        let _ = writeln!(self.out, "{{\"events\":[{}]}}", self.events.join(","));
+        self.events.clear();
+    }
+}
+
+/// One JSON object per line, written to stdout (or `out`) as each event
+/// happens.
+pub struct NdjsonSink<W = io::Stdout> {
+    out: W,
+}
+
+impl<W: Write> NdjsonSink<W> {
+    pub fn new(out: W) -> Self {
+        Self { out }
+    }
+}
+
+impl Default for NdjsonSink {
+    fn default() -> Self {
+        Self::new(io::stdout())
+    }
+}
+
+impl<W: Write> DiagnosticSink for NdjsonSink<W> {
+    fn emit(&mut self, diagnostic: Diagnostic) {
+        let _ = writeln!(self.out, "{}", diagnostic.to_json());
+    }
+}
+
+/// The `--format` choices.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Format {
+    #[default]
+    Text,
+    Json,
+    Ndjson,
+}
+
+impl Format {
+    /// A sink for this format. Only text is ever colored.
+    pub fn sink(self, color: ColorChoice) -> Box<dyn DiagnosticSink> {
+        match self {
+            Format::Text => Box::new(TextSink::new(color)),
+            Format::Json => Box::new(JsonSink::default()),
+            Format::Ndjson => Box::new(NdjsonSink::default()),
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            "ndjson" => Ok(Format::Ndjson),
+            _ => Err(format!("unknown format {s:?}")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_fields_are_flattened() {
+        let diagnostic = Diagnostic::warn("thread-count", "Threads")
+            .field("before", 1usize)
+            .field("linux", true)
+            .field("name", "smithay");
+        assert_eq!(
+            diagnostic.to_json(),
+            r#"{"level":"warn","event":"thread-count","message":"Threads","before":1,"linux":true,"name":"smithay"}"#
+        );
+    }
+
+    #[test]
+    fn json_strings_are_escaped() {
+        let diagnostic = Diagnostic::info("closing", "say \"hi\"\\\n\tnow\u{1}");
+        assert_eq!(
+            diagnostic.to_json(),
+            r#"{"level":"info","event":"closing","message":"say \"hi\"\\\n\tnow\u0001"}"#
+        );
+    }
+
+    #[test]
+    fn ndjson_is_one_object_per_line() {
+        let diagnostic = Diagnostic::info("dropping", "Event loop exited\r\ndropping App...");
+        let json = diagnostic.to_json();
+        assert!(!json.contains(['\n', '\r']), "{json}");
+        assert!(json.starts_with('{') && json.ends_with('}'));
+    }
+
+    #[test]
+    fn text_never_renders_the_bare_message() {
+        let sink = TextSink::new(ColorChoice::Never);
+        let diagnostic = Diagnostic::error("window-failed", "No window");
+        assert_eq!(sink.render(&diagnostic, &io::stdout()), "No window");
+        assert_eq!(sink.render(&diagnostic, &io::stderr()), "No window");
+    }
+
+    #[test]
+    fn text_lists_shown_fields() {
+        let sink = TextSink::new(ColorChoice::Never);
+        let diagnostic = Diagnostic::info("plan", "Dry run")
+            .field("timeout_ms", 300u64)
+            .field("ordered_exit", true)
+            .field("format", "Text");
+        assert_eq!(sink.render(&diagnostic, &io::stdout()), "Dry run");
+        assert_eq!(
+            sink.render(&diagnostic.show_fields(), &io::stdout()),
+            "Dry run\n  timeout_ms: 300\n  ordered_exit: true\n  format: Text"
+        );
+    }
+
+    #[test]
+    fn text_always_paints() {
+        let sink = TextSink::new(ColorChoice::Always);
+        let diagnostic = Diagnostic::warn("oversize", "Too big");
+        assert_eq!(
+            sink.render(&diagnostic, &io::stdout()),
+            "\x1b[33mToo big\x1b[0m"
+        );
+    }
+
+    /// One diagnostic per [`Value`] variant, plus a message that needs
+    /// escaping.
+    fn every_value() -> Vec<Diagnostic> {
+        vec![
+            Diagnostic::info("window-created", "Window created").field("linux", true),
+            Diagnostic::warn("thread-count", "Threads").field("before", -3i64),
+            Diagnostic::error("window-failed", "say \"hi\"\r\n\tnow\u{1}")
+                .field("name", "smithay \\ \"clipboard\""),
+        ]
+    }
+
+    fn assert_parses_as(json: &serde_json::Value, diagnostic: &Diagnostic) {
+        assert_eq!(json["level"], diagnostic.level.name());
+        assert_eq!(json["event"], diagnostic.event);
+        assert_eq!(json["message"], diagnostic.message.as_str());
+        for (name, value) in &diagnostic.fields {
+            let expected = match value {
+                Value::Bool(value) => serde_json::Value::from(*value),
+                Value::Int(value) => serde_json::Value::from(*value),
+                Value::Str(value) => serde_json::Value::from(value.as_str()),
+            };
+            assert_eq!(json[name], expected, "{name}");
+        }
+    }
+
+    #[test]
+    fn ndjson_sink_lines_parse() {
+        let mut out = Vec::new();
+        let mut sink = NdjsonSink::new(&mut out);
+        for diagnostic in every_value() {
+            sink.emit(diagnostic);
+        }
+        sink.finish();
+
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<_> = out.lines().collect();
+        assert_eq!(lines.len(), every_value().len(), "{out}");
+        for (line, diagnostic) in lines.iter().zip(every_value()) {
+            let json: serde_json::Value = serde_json::from_str(line).unwrap();
+            assert_parses_as(&json, &diagnostic);
+        }
+    }
+
+    #[test]
+    fn json_sink_writes_one_object_on_finish() {
+        let mut out = Vec::new();
+        let mut sink = JsonSink::new(&mut out);
+        for diagnostic in every_value() {
+            sink.emit(diagnostic);
+        }
+        sink.finish();
+
+        let json: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        let events = json["events"].as_array().unwrap();
+        assert_eq!(events.len(), every_value().len());
+        for (event, diagnostic) in events.iter().zip(every_value()) {
+            assert_parses_as(event, &diagnostic);
+        }
+    }
+}
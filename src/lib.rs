@@ -0,0 +1,21 @@
+//! The egui-winit clipboard segfault reproduction, as a library.
+//!
+//! The binary in `main.rs` only parses arguments and calls [`run_once`], so
+//! the whole run can also be driven in-process.
+
+mod affinity;
+mod app;
+pub mod color;
+pub mod config;
+pub mod diagnostics;
+pub mod record;
+#[cfg(feature = "rwh05")]
+pub mod rwh05;
+pub mod safe_clipboard;
+pub mod supervisor;
+pub mod timing;
+pub mod trace;
+
+pub use app::{run_once, run_with, RunOutcome, ShutdownReason, ThreadCounts, WindowFactory};
+pub use config::ClipboardConfig;
+pub use safe_clipboard::SafeClipboard;
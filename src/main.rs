@@ -16,100 +16,66 @@
 //! This is 100% safe Rust code - NO unsafe blocks - yet it segfaults.
 //!
 //! Run with: cargo run --release
-//! The window will auto-close after 1 second.
+//! The window will auto-close after 1 second (see `--timeout`).
 
-use std::sync::Arc;
-use std::time::{Duration, Instant};
-use winit::application::ApplicationHandler;
-use winit::event::WindowEvent;
-use winit::event_loop::{ActiveEventLoop, ControlFlow, EventLoop};
-use winit::raw_window_handle::{HasDisplayHandle, RawDisplayHandle};
-use winit::window::{Window, WindowId};
+use smithay_clipboard_segfault::config::{ClipboardConfig, USAGE};
+use smithay_clipboard_segfault::diagnostics::Format;
+use smithay_clipboard_segfault::{run_once, supervisor, ShutdownReason};
 
-struct App {
-    // Window declared BEFORE clipboard - this means window drops LAST.
-    // This is the "wrong" order that triggers the bug, but it's completely
-    // reasonable code that a user might write. Nothing warns about this.
-    //
-    // NOTE: Swapping the order of `window` and `clipboard` fields would
-    // "fix" the segfault because Rust drops fields in declaration order.
-    // But that's exactly the point - a safe API should not segfault based
-    // on field ordering! The compiler gives no warning about this.
-    window: Option<Arc<Window>>,
-    clipboard: Option<egui_winit::clipboard::Clipboard>,
-    start_time: Option<Instant>,
-}
-
-impl ApplicationHandler for App {
-    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
-        if self.window.is_none() {
-            let window = Arc::new(
-                event_loop
-                    .create_window(Window::default_attributes().with_title("Will auto-close in 1s"))
-                    .unwrap(),
-            );
-
-            // Get the display handle
-            let raw_display = window.display_handle().ok().map(|h| h.as_raw());
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let config = ClipboardConfig::from_args(args.clone()).unwrap_or_else(|err| {
+        eprintln!("{err}");
+        eprintln!("{USAGE}");
+        std::process::exit(2);
+    });
 
-            // Check if we're on Wayland
-            if let Some(RawDisplayHandle::Wayland(_)) = raw_display {
-                // Create egui-winit clipboard using the SAFE API
-                // Note: NO unsafe block here! This is the soundness bug.
-                let clipboard = egui_winit::clipboard::Clipboard::new(raw_display);
-                self.clipboard = Some(clipboard);
-                println!("Created egui-winit Clipboard (safe API, no unsafe block!)");
-            } else {
-                println!("Not running on Wayland, segfault won't occur");
+    // A dry run only prints the plan, so there's no crash to expect
+    if config.expect_crash && !config.dry_run {
+        let child_args: Vec<String> = args
+            .into_iter()
+            .filter(|arg| arg != "--expect-crash")
+            .collect();
+        match supervisor::expect_crash(&child_args, config.sink().as_mut()) {
+            Ok(true) => std::process::exit(0),
+            Ok(false) => std::process::exit(1),
+            Err(err) => {
+                eprintln!("Can't run child process: {err}");
+                std::process::exit(1);
             }
-
-            self.window = Some(window);
-            self.start_time = Some(Instant::now());
-
-            // Request continuous polling so we can check the timer
-            event_loop.set_control_flow(ControlFlow::Poll);
         }
     }
 
-    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
-        // Auto-close after 1 second
-        if let Some(start) = self.start_time {
-            if start.elapsed() >= Duration::from_secs(1) {
-                println!("Auto-closing window after 1 second...");
-                println!("Watch for SEGFAULT - this is 100% safe Rust code!");
-                event_loop.exit();
-            }
-        }
+    // Structured formats get nothing but the diagnostics on stdout, and
+    // --wait-for-owner nothing but the content
+    if config.format == Format::Text
+        && !config.trace_stdout
+        && !config.dry_run
+        && !config.wait_for_owner
+    {
+        println!("Demonstrating egui-winit soundness bug");
+        println!("======================================");
+        println!();
+        println!("This program uses ONLY safe Rust - no unsafe blocks.");
+        println!("Yet it will segfault on Wayland due to egui-winit wrapping");
+        println!("an unsafe API (smithay-clipboard) in a safe interface.");
+        println!();
     }
 
-    fn window_event(&mut self, event_loop: &ActiveEventLoop, _id: WindowId, event: WindowEvent) {
-        if let WindowEvent::CloseRequested = event {
-            println!("Window close requested, exiting...");
-            event_loop.exit();
+    let assert_no_leak = config.assert_no_leak;
+    match run_once(config) {
+        Ok(outcome)
+            if outcome
+                .shutdown_reason
+                .is_some_and(ShutdownReason::is_failure)
+                || (assert_no_leak && outcome.clipboard_alive) =>
+        {
+            std::process::exit(1)
+        }
+        Ok(_) => {}
+        Err(err) => {
+            eprintln!("Run failed: {err}");
+            std::process::exit(1);
         }
     }
 }
-
-fn main() {
-    println!("Demonstrating egui-winit soundness bug");
-    println!("======================================");
-    println!();
-    println!("This program uses ONLY safe Rust - no unsafe blocks.");
-    println!("Yet it will segfault on Wayland due to egui-winit wrapping");
-    println!("an unsafe API (smithay-clipboard) in a safe interface.");
-    println!();
-    println!("Window will auto-close in 1 second...");
-    println!();
-
-    let event_loop = EventLoop::new().unwrap();
-    let mut app = App {
-        window: None,
-        clipboard: None,
-        start_time: None,
-    };
-
-    event_loop.run_app(&mut app).unwrap();
-
-    println!("Event loop exited, dropping App...");
-    println!("(window drops first, then clipboard tries to use invalid display)");
-}
@@ -0,0 +1,231 @@
+//! Recording and replaying the lifecycle of a session.
+//!
+//! `--record <path>` writes one line per lifecycle event or clipboard
+//! operation, as milliseconds since startup followed by the event name:
+//!
+//! ```text
+//! 0 window-created
+//! 2 clipboard-created
+//! 250 clipboard-get
+//! 1003 exit
+//! ```
+//!
+//! `--replay <path>` reads such a file back and re-runs the same sequence on
+//! the same schedule, so a crashing session can be captured once and
+//! reproduced while bisecting. Only the operations are recorded, not the
+//! text, so replayed sets write placeholder text.
+//! Each line is flushed as soon as it's written, so a recording survives the
+//! segfault it's trying to capture.
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, LineWriter, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// A lifecycle event worth recording.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    WindowCreated,
+    ClipboardCreated,
+    ClipboardSet,
+    ClipboardGet,
+    /// The window was dropped with `D`.
+    WindowDropped,
+    /// The clipboard was dropped with `C`.
+    ClipboardDropped,
+    CloseRequested,
+    Exit,
+}
+
+impl Event {
+    pub fn name(self) -> &'static str {
+        match self {
+            Event::WindowCreated => "window-created",
+            Event::ClipboardCreated => "clipboard-created",
+            Event::ClipboardSet => "clipboard-set",
+            Event::ClipboardGet => "clipboard-get",
+            Event::WindowDropped => "window-dropped",
+            Event::ClipboardDropped => "clipboard-dropped",
+            Event::CloseRequested => "close-requested",
+            Event::Exit => "exit",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "window-created" => Some(Event::WindowCreated),
+            "clipboard-created" => Some(Event::ClipboardCreated),
+            "clipboard-set" => Some(Event::ClipboardSet),
+            "clipboard-get" => Some(Event::ClipboardGet),
+            "window-dropped" => Some(Event::WindowDropped),
+            "clipboard-dropped" => Some(Event::ClipboardDropped),
+            "close-requested" => Some(Event::CloseRequested),
+            "exit" => Some(Event::Exit),
+            _ => None,
+        }
+    }
+}
+
+/// Writes events to a log file as they happen.
+pub struct Recorder {
+    origin: Instant,
+    out: LineWriter<File>,
+}
+
+impl Recorder {
+    pub fn create(path: &Path) -> io::Result<Self> {
+        Ok(Self {
+            origin: Instant::now(),
+            out: LineWriter::new(File::create(path)?),
+        })
+    }
+
+    pub fn record(&mut self, event: Event) -> io::Result<()> {
+        let millis = self.origin.elapsed().as_millis();
+        writeln!(self.out, "{millis} {}", event.name())
+    }
+}
+
+/// A recorded session being played back.
+pub struct Replay {
+    events: VecDeque<(Duration, Event)>,
+}
+
+impl Replay {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let mut events = VecDeque::new();
+        for (index, line) in BufReader::new(File::open(path)?).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let parsed = line.split_once(' ').and_then(|(millis, name)| {
+                Some((
+                    Duration::from_millis(millis.parse().ok()?),
+                    Event::from_name(name.trim())?,
+                ))
+            });
+            match parsed {
+                Some(event) => events.push_back(event),
+                None => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!(
+                            "line {}: expected `<millis> <event>`, got {line:?}",
+                            index + 1
+                        ),
+                    ))
+                }
+            }
+        }
+        Ok(Self { events })
+    }
+
+    /// Whether the recorded session contained `event` at all.
+    pub fn contains(&self, event: Event) -> bool {
+        self.events.iter().any(|&(_, recorded)| recorded == event)
+    }
+
+    /// Whether every recorded event has been replayed.
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Pop the next event if its recorded time has been reached.
+    pub fn next_due(&mut self, elapsed: Duration) -> Option<Event> {
+        match self.events.front() {
+            Some(&(at, event)) if at <= elapsed => {
+                self.events.pop_front();
+                Some(event)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!(
+            "smithay-clipboard-segfault-{}-{name}",
+            std::process::id()
+        ))
+    }
+
+    #[test]
+    fn record_then_replay() {
+        let path = temp_path("round-trip");
+        let mut recorder = Recorder::create(&path).unwrap();
+        for event in [
+            Event::WindowCreated,
+            Event::ClipboardCreated,
+            Event::ClipboardSet,
+            Event::ClipboardGet,
+            Event::WindowDropped,
+            Event::ClipboardDropped,
+            Event::Exit,
+        ] {
+            recorder.record(event).unwrap();
+        }
+        drop(recorder);
+
+        let mut replay = Replay::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(replay.contains(Event::ClipboardCreated));
+        assert!(!replay.contains(Event::CloseRequested));
+
+        let mut replayed = Vec::new();
+        while let Some(event) = replay.next_due(Duration::from_secs(60)) {
+            replayed.push(event);
+        }
+        assert_eq!(
+            replayed,
+            [
+                Event::WindowCreated,
+                Event::ClipboardCreated,
+                Event::ClipboardSet,
+                Event::ClipboardGet,
+                Event::WindowDropped,
+                Event::ClipboardDropped,
+                Event::Exit,
+            ]
+        );
+        assert!(replay.is_empty());
+    }
+
+    #[test]
+    fn events_wait_for_their_time() {
+        let path = temp_path("schedule");
+        std::fs::write(&path, "0 window-created\n\n500 clipboard-get\n1000 exit\n").unwrap();
+        let mut replay = Replay::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(replay.next_due(Duration::ZERO), Some(Event::WindowCreated));
+        assert_eq!(replay.next_due(Duration::from_millis(499)), None);
+        assert_eq!(
+            replay.next_due(Duration::from_millis(500)),
+            Some(Event::ClipboardGet)
+        );
+        assert_eq!(replay.next_due(Duration::from_millis(999)), None);
+        assert!(!replay.is_empty());
+        assert_eq!(
+            replay.next_due(Duration::from_millis(1000)),
+            Some(Event::Exit)
+        );
+        assert!(replay.is_empty());
+    }
+
+    #[test]
+    fn malformed_line_is_invalid_data() {
+        let path = temp_path("malformed");
+        std::fs::write(&path, "0 window-created\nsoon exit\n").unwrap();
+        let err = Replay::load(&path).err().unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().starts_with("line 2:"), "{err}");
+    }
+}
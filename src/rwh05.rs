@@ -0,0 +1,97 @@
+//! Compatibility with `raw-window-handle` 0.5 display handles.
+//!
+//! winit 0.30 and egui-winit speak `raw-window-handle` 0.6, but some stacks
+//! still hand out 0.5 handles. This converts them so they can be passed to
+//! `egui_winit::clipboard::Clipboard::new` unchanged.
+//!
+//! [`SafeClipboard`](crate::SafeClipboard) has no use for it: it takes the
+//! display from a winit [`Window`](winit::window::Window), which always
+//! speaks 0.6.
+
+use std::ptr::NonNull;
+
+use winit::raw_window_handle::{
+    RawDisplayHandle, WaylandDisplayHandle, XcbDisplayHandle, XlibDisplayHandle,
+};
+
+/// Convert a `raw-window-handle` 0.5 display handle to its 0.6 equivalent.
+///
+/// Only the Wayland and X11 (Xlib and XCB) variants are mapped, since those
+/// are the only ones the clipboard cares about. Returns `None` for any other
+/// variant, and for a Wayland handle with a null display pointer, which 0.6
+/// cannot represent. `None` is also what `Clipboard::new` expects when no
+/// display is available, so the result can be passed straight through.
+pub fn to_rwh06(old: rwh05::RawDisplayHandle) -> Option<RawDisplayHandle> {
+    match old {
+        rwh05::RawDisplayHandle::Wayland(handle) => {
+            let display = NonNull::new(handle.display)?;
+            Some(WaylandDisplayHandle::new(display).into())
+        }
+        rwh05::RawDisplayHandle::Xlib(handle) => {
+            Some(XlibDisplayHandle::new(NonNull::new(handle.display), handle.screen).into())
+        }
+        rwh05::RawDisplayHandle::Xcb(handle) => {
+            Some(XcbDisplayHandle::new(NonNull::new(handle.connection), handle.screen).into())
+        }
+        _ => None,
+    }
+}
+
+#[cfg(all(test, feature = "rwh05"))]
+mod tests {
+    use super::*;
+
+    fn pointer() -> *mut std::ffi::c_void {
+        NonNull::<u8>::dangling().as_ptr().cast()
+    }
+
+    #[test]
+    fn wayland() {
+        let mut old = rwh05::WaylandDisplayHandle::empty();
+        old.display = pointer();
+        match to_rwh06(old.into()) {
+            Some(RawDisplayHandle::Wayland(new)) => assert_eq!(new.display.as_ptr(), pointer()),
+            other => panic!("expected a Wayland handle, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn wayland_null_display() {
+        let old = rwh05::WaylandDisplayHandle::empty();
+        assert_eq!(to_rwh06(old.into()), None);
+    }
+
+    #[test]
+    fn xlib() {
+        let mut old = rwh05::XlibDisplayHandle::empty();
+        old.display = pointer();
+        old.screen = 1;
+        match to_rwh06(old.into()) {
+            Some(RawDisplayHandle::Xlib(new)) => {
+                assert_eq!(new.display.map(NonNull::as_ptr), Some(pointer()));
+                assert_eq!(new.screen, 1);
+            }
+            other => panic!("expected an Xlib handle, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn xcb() {
+        let mut old = rwh05::XcbDisplayHandle::empty();
+        old.connection = pointer();
+        old.screen = 2;
+        match to_rwh06(old.into()) {
+            Some(RawDisplayHandle::Xcb(new)) => {
+                assert_eq!(new.connection.map(NonNull::as_ptr), Some(pointer()));
+                assert_eq!(new.screen, 2);
+            }
+            other => panic!("expected an XCB handle, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn unmapped_variant() {
+        let old = rwh05::WindowsDisplayHandle::empty();
+        assert_eq!(to_rwh06(old.into()), None);
+    }
+}
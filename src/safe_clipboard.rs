@@ -0,0 +1,192 @@
+//! A clipboard whose teardown order is part of the API instead of a convention.
+//!
+//! [`SafeClipboard::attach`] splits ownership in two: a [`ClipboardGuard`]
+//! that owns the clipboard and a [`WindowToken`] that owns the window. The
+//! only way to get the window back is [`WindowToken::release`], which takes
+//! the guard by value and drops the clipboard first. Field order in the
+//! caller's struct no longer decides whether teardown is sound.
+//!
+//! The event loop holds the display connection too, so the guard must also
+//! be released before the loop exits (e.g. in `ApplicationHandler::exiting`).
+//!
+//! ```
+//! use std::sync::Arc;
+//! use smithay_clipboard_segfault::SafeClipboard;
+//! use winit::window::Window;
+//!
+//! fn use_and_tear_down(window: Arc<Window>) -> Arc<Window> {
+//!     let (mut guard, token) = SafeClipboard::attach(window);
+//!     guard.set_text("hello".to_owned());
+//!     // Hands the window back only after dropping the clipboard
+//!     token.release(guard)
+//! }
+//! ```
+//!
+//! Using the clipboard after `release` doesn't compile, since the guard has
+//! been moved:
+//!
+//! ```compile_fail,E0382
+//! use std::sync::Arc;
+//! use smithay_clipboard_segfault::safe_clipboard::{ClipboardGuard, WindowToken};
+//! use winit::window::Window;
+//!
+//! fn use_after_release(mut guard: ClipboardGuard, token: WindowToken) -> Arc<Window> {
+//!     let window = token.release(guard);
+//!     guard.set_text("too late".to_owned());
+//!     window
+//! }
+//! ```
+
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Weak};
+use winit::raw_window_handle::HasDisplayHandle;
+use winit::window::Window;
+
+/// A clipboard that can only be created together with a [`WindowToken`].
+///
+/// Neither `Send` nor `Sync`: its assumptions about the display hold on the
+/// thread that owns the event loop, so it stays there. Moving it into
+/// `thread::spawn` is a compile error.
+///
+/// ```compile_fail
+/// use smithay_clipboard_segfault::safe_clipboard::ClipboardGuard;
+///
+/// fn move_to_thread(guard: ClipboardGuard) {
+///     std::thread::spawn(move || drop(guard));
+/// }
+/// ```
+pub struct SafeClipboard {
+    inner: egui_winit::clipboard::Clipboard,
+    _not_send: PhantomData<*const ()>,
+}
+
+impl SafeClipboard {
+    /// Create a clipboard on `window`'s display.
+    pub fn attach(window: Arc<Window>) -> (ClipboardGuard, WindowToken) {
+        let raw_display = window.display_handle().ok().map(|h| h.as_raw());
+        let attached = Arc::new(());
+        let token = WindowToken {
+            window: Some(window),
+            guard: Arc::downgrade(&attached),
+        };
+        let guard = ClipboardGuard {
+            clipboard: SafeClipboard {
+                inner: egui_winit::clipboard::Clipboard::new(raw_display),
+                _not_send: PhantomData,
+            },
+            attached,
+        };
+        (guard, token)
+    }
+
+    pub fn get(&mut self) -> Option<String> {
+        self.inner.get()
+    }
+
+    pub fn set_text(&mut self, text: String) {
+        self.inner.set_text(text);
+    }
+}
+
+/// Owns the clipboard half of [`SafeClipboard::attach`].
+pub struct ClipboardGuard {
+    clipboard: SafeClipboard,
+    // Keeps the token's `Weak` alive for as long as the clipboard is
+    attached: Arc<()>,
+}
+
+impl Deref for ClipboardGuard {
+    type Target = SafeClipboard;
+
+    fn deref(&self) -> &SafeClipboard {
+        &self.clipboard
+    }
+}
+
+impl DerefMut for ClipboardGuard {
+    fn deref_mut(&mut self) -> &mut SafeClipboard {
+        &mut self.clipboard
+    }
+}
+
+/// Owns the window half of [`SafeClipboard::attach`].
+pub struct WindowToken {
+    window: Option<Arc<Window>>,
+    guard: Weak<()>,
+}
+
+impl WindowToken {
+    pub fn window(&self) -> &Arc<Window> {
+        self.window
+            .as_ref()
+            .expect("window is only taken by release")
+    }
+
+    /// Drop the clipboard, then hand the window back.
+    ///
+    /// Panics if `guard` came from a different [`SafeClipboard::attach`].
+    pub fn release(mut self, guard: ClipboardGuard) -> Arc<Window> {
+        check_attached(&self.guard, &guard.attached);
+        drop(guard);
+        self.window.take().expect("window is only taken by release")
+    }
+}
+
+impl Drop for WindowToken {
+    fn drop(&mut self) {
+        // The window holds its own clone of the display connection, so it
+        // can be the last owner. Dropping it while the clipboard is alive
+        // could close the display under it; leak the window instead.
+        forget_if_attached(self.window.take(), &self.guard);
+    }
+}
+
+/// Panics unless `attached` is the guard `token` was created with.
+fn check_attached(token: &Weak<()>, attached: &Arc<()>) {
+    assert!(
+        Weak::ptr_eq(token, &Arc::downgrade(attached)),
+        "ClipboardGuard released with a WindowToken it wasn't attached to"
+    );
+}
+
+/// Drop `value`, unless the guard behind `guard` is still alive.
+fn forget_if_attached<T>(value: Option<T>, guard: &Weak<()>) {
+    if guard.strong_count() > 0 {
+        std::mem::forget(value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matching_guard_is_accepted() {
+        let attached = Arc::new(());
+        check_attached(&Arc::downgrade(&attached), &attached);
+    }
+
+    #[test]
+    #[should_panic(expected = "wasn't attached to")]
+    fn mismatched_guard_panics() {
+        let attached = Arc::new(());
+        check_attached(&Arc::downgrade(&attached), &Arc::new(()));
+    }
+
+    #[test]
+    fn window_is_leaked_while_the_guard_is_alive() {
+        let window = Arc::new(());
+        let attached = Arc::new(());
+        forget_if_attached(Some(Arc::clone(&window)), &Arc::downgrade(&attached));
+        assert_eq!(Arc::strong_count(&window), 2);
+    }
+
+    #[test]
+    fn window_is_dropped_once_the_guard_is_gone() {
+        let window = Arc::new(());
+        let guard = Arc::downgrade(&Arc::new(()));
+        forget_if_attached(Some(Arc::clone(&window)), &guard);
+        assert_eq!(Arc::strong_count(&window), 1);
+    }
+}
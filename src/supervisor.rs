@@ -0,0 +1,46 @@
+//! Re-running the reproduction in a child process and inspecting how it died.
+//!
+//! `--expect-crash` turns the bug into a "known failing" CI gate: the
+//! supervisor runs the same binary again without the flag and succeeds only
+//! if the child segfaulted. Once the bug is fixed, the gate flips.
+
+use std::io;
+use std::process::{Command, ExitStatus};
+
+use crate::diagnostics::{Diagnostic, DiagnosticSink};
+
+/// Run this executable again with `args` and report whether it segfaulted.
+pub fn expect_crash(args: &[String], sink: &mut dyn DiagnosticSink) -> io::Result<bool> {
+    let status = Command::new(std::env::current_exe()?).args(args).status()?;
+    let crashed = is_segfault(status);
+    if crashed {
+        sink.emit(
+            Diagnostic::info("expected-crash", "Reproduced expected crash (SIGSEGV)")
+                .field("status", status.to_string()),
+        );
+    } else {
+        sink.emit(
+            Diagnostic::error(
+                "expected-crash",
+                format!("Expected the child to crash with SIGSEGV, got {status}"),
+            )
+            .field("status", status.to_string()),
+        );
+    }
+    sink.finish();
+    Ok(crashed)
+}
+
+#[cfg(unix)]
+fn is_segfault(status: ExitStatus) -> bool {
+    use std::os::unix::process::ExitStatusExt;
+
+    const SIGSEGV: i32 = 11;
+    status.signal() == Some(SIGSEGV)
+}
+
+#[cfg(windows)]
+fn is_segfault(status: ExitStatus) -> bool {
+    const STATUS_ACCESS_VIOLATION: u32 = 0xC000_0005;
+    status.code() == Some(STATUS_ACCESS_VIOLATION as i32)
+}
@@ -0,0 +1,149 @@
+//! How long individual clipboard operations take.
+//!
+//! Every `set_text` and `get` the app makes is timed and kept in a bounded
+//! ring buffer, so burn-in and hold runs report min/max/avg latency through
+//! [`RunOutcome::op_timings`](crate::RunOutcome::op_timings) without a
+//! separate harness.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// How many of the most recent operations are kept.
+const CAPACITY: usize = 4096;
+
+/// A timed clipboard operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Set,
+    Get,
+}
+
+impl Op {
+    pub fn name(self) -> &'static str {
+        match self {
+            Op::Set => "set",
+            Op::Get => "get",
+        }
+    }
+}
+
+/// Summary of the retained timings for one [`Op`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OpStats {
+    /// Number of timings the summary covers, at most the buffer capacity.
+    pub count: usize,
+    pub min: Duration,
+    pub max: Duration,
+    pub avg: Duration,
+}
+
+/// The most recent operation timings.
+#[derive(Debug, Clone, Default)]
+pub struct OpTimings {
+    timings: VecDeque<(Op, Duration)>,
+}
+
+impl OpTimings {
+    /// Run `f`, recording how long it took as `op`.
+    pub fn time<T>(&mut self, op: Op, f: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let result = f();
+        self.record(op, start.elapsed());
+        result
+    }
+
+    pub fn record(&mut self, op: Op, duration: Duration) {
+        if self.timings.len() == CAPACITY {
+            self.timings.pop_front();
+        }
+        self.timings.push_back((op, duration));
+    }
+
+    /// Min/max/avg of the retained timings for `op`, or `None` if there are
+    /// none.
+    pub fn stats(&self, op: Op) -> Option<OpStats> {
+        let mut durations = self
+            .timings
+            .iter()
+            .filter(|&&(recorded, _)| recorded == op)
+            .map(|&(_, duration)| duration);
+        let first = durations.next()?;
+        let (mut count, mut min, mut max, mut total) = (1, first, first, first);
+        for duration in durations {
+            count += 1;
+            min = min.min(duration);
+            max = max.max(duration);
+            total += duration;
+        }
+        Some(OpStats {
+            count,
+            min,
+            max,
+            avg: total / count as u32,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn stats_per_op() {
+        let mut timings = OpTimings::default();
+        for (op, millis) in [(Op::Set, 2), (Op::Get, 10), (Op::Set, 6), (Op::Set, 4)] {
+            timings.record(op, ms(millis));
+        }
+        assert_eq!(
+            timings.stats(Op::Set),
+            Some(OpStats {
+                count: 3,
+                min: ms(2),
+                max: ms(6),
+                avg: ms(4),
+            })
+        );
+        assert_eq!(
+            timings.stats(Op::Get),
+            Some(OpStats {
+                count: 1,
+                min: ms(10),
+                max: ms(10),
+                avg: ms(10),
+            })
+        );
+    }
+
+    #[test]
+    fn no_stats_without_timings() {
+        assert_eq!(OpTimings::default().stats(Op::Get), None);
+    }
+
+    #[test]
+    fn time_records_the_op() {
+        let mut timings = OpTimings::default();
+        assert_eq!(timings.time(Op::Get, || 7), 7);
+        assert_eq!(timings.stats(Op::Get).map(|stats| stats.count), Some(1));
+        assert_eq!(timings.stats(Op::Set), None);
+    }
+
+    #[test]
+    fn oldest_timings_are_evicted_at_capacity() {
+        let mut timings = OpTimings::default();
+        timings.record(Op::Set, ms(100));
+        for _ in 1..CAPACITY {
+            timings.record(Op::Get, ms(1));
+        }
+        assert_eq!(timings.stats(Op::Set).map(|stats| stats.count), Some(1));
+
+        timings.record(Op::Get, ms(3));
+        assert_eq!(timings.stats(Op::Set), None);
+        let get = timings.stats(Op::Get).unwrap();
+        assert_eq!(get.count, CAPACITY);
+        assert_eq!(get.max, ms(3));
+    }
+}
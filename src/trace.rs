@@ -0,0 +1,85 @@
+//! A compact, column-aligned event trace for `--trace-stdout`.
+//!
+//! Unlike the free-form text output, every line has the same shape, so the
+//! teardown ordering that causes the crash can be read straight down:
+//!
+//! ```text
+//!        412us  window-created      Created window
+//!       1630us  clipboard-created   Created egui-winit Clipboard (safe API, no unsafe block!)
+//!    1000118us  exit                Exiting event loop
+//!    1000342us  dropping            Event loop exited, dropping App...
+//! ```
+
+use std::io;
+use std::time::Instant;
+
+use crate::color::{self, ColorChoice};
+use crate::diagnostics::{Diagnostic, DiagnosticSink};
+
+/// Prints each diagnostic as one line, timestamped in microseconds since the
+/// sink was created.
+pub struct TraceSink {
+    origin: Instant,
+    color: bool,
+}
+
+impl TraceSink {
+    pub fn new(color: ColorChoice) -> Self {
+        Self {
+            origin: Instant::now(),
+            color: color.enabled(&io::stdout()),
+        }
+    }
+}
+
+impl Default for TraceSink {
+    fn default() -> Self {
+        Self::new(ColorChoice::default())
+    }
+}
+
+impl TraceSink {
+    /// The trace line for `diagnostic`, emitted `micros` after the origin.
+    fn line(&self, micros: u128, diagnostic: &Diagnostic) -> String {
+        let message = diagnostic.message.lines().next().unwrap_or_default();
+        let line = format!("{:<18}  {message}", diagnostic.event);
+        if self.color {
+            format!("{micros:>10}us  {}", color::paint(diagnostic, &line))
+        } else {
+            format!("{micros:>10}us  {line}")
+        }
+    }
+}
+
+impl DiagnosticSink for TraceSink {
+    fn emit(&mut self, diagnostic: Diagnostic) {
+        let micros = self.origin.elapsed().as_micros();
+        println!("{}", self.line(micros, &diagnostic));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn never_is_plain() {
+        let sink = TraceSink::new(ColorChoice::Never);
+        let line = sink.line(
+            412,
+            &Diagnostic::error("window-failed", "No window\nat all"),
+        );
+        assert!(!line.contains('\x1b'), "{line:?}");
+        assert_eq!(line, "       412us  window-failed       No window");
+    }
+
+    #[test]
+    fn always_paints_the_event_and_message() {
+        let sink = TraceSink::new(ColorChoice::Always);
+        let line = sink.line(7, &Diagnostic::info("dropping", "Bye"));
+        assert_eq!(
+            line,
+            "         7us  \x1b[1;36mdropping            Bye\x1b[0m"
+        );
+    }
+}
@@ -0,0 +1,197 @@
+//! End-to-end runs of `run_once` against a real compositor.
+//!
+//! winit wants its event loop on the main thread and allows only one per
+//! process, so this runs without the libtest harness: each case re-runs this
+//! binary with `GUI_TEST_CASE` set and fails if that child does. Skipped
+//! unless `RUN_GUI_TESTS=1`.
+
+use std::process::{Command, ExitCode};
+use std::time::Duration;
+
+use smithay_clipboard_segfault::{run_once, run_with, ClipboardConfig, RunOutcome, ShutdownReason};
+
+const CASES: &[(&str, fn())] = &[
+    ("timeout", timeout),
+    ("frame_limit", frame_limit),
+    ("ordered_exit", ordered_exit),
+    ("forced_leak", forced_leak),
+    ("leak_exit_status", leak_exit_status),
+    ("watch_interrupted", watch_interrupted),
+    ("wait_timeout", wait_timeout),
+    ("window_error", window_error),
+];
+
+fn main() -> ExitCode {
+    if let Ok(name) = std::env::var("GUI_TEST_CASE") {
+        let (_, case) = CASES
+            .iter()
+            .find(|(case, _)| *case == name)
+            .expect("unknown GUI_TEST_CASE");
+        case();
+        return ExitCode::SUCCESS;
+    }
+    if std::env::var("RUN_GUI_TESTS").as_deref() != Ok("1") {
+        println!("Skipping GUI tests, set RUN_GUI_TESTS=1 to run them");
+        return ExitCode::SUCCESS;
+    }
+
+    let exe = std::env::current_exe().expect("can't find the test binary");
+    let mut failed = 0;
+    for (name, _) in CASES {
+        let status = Command::new(&exe)
+            .env("GUI_TEST_CASE", name)
+            .status()
+            .expect("can't run the test binary");
+        if status.success() {
+            println!("test {name} ... ok");
+        } else {
+            println!("test {name} ... FAILED ({status})");
+            failed += 1;
+        }
+    }
+    if failed == 0 {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+/// Whether a clipboard will be created, which only happens on Wayland.
+/// Cases that need one skip instead of passing vacuously.
+fn on_wayland() -> bool {
+    let wayland = std::env::var_os("WAYLAND_DISPLAY").is_some();
+    if !wayland {
+        println!("Not on Wayland, so there's no clipboard, skipping");
+    }
+    wayland
+}
+
+/// Run with `config`, dropping the clipboard in `exiting()` so the run
+/// survives its own teardown.
+fn run(config: ClipboardConfig) -> RunOutcome {
+    run_once(ClipboardConfig {
+        ordered_exit: true,
+        ..config
+    })
+    .expect("run failed")
+}
+
+/// The default run: the window auto-closes once the timeout elapses.
+fn timeout() {
+    let outcome = run(ClipboardConfig {
+        timeout: Duration::from_millis(300),
+        ..ClipboardConfig::default()
+    });
+    assert_eq!(outcome.shutdown_reason, Some(ShutdownReason::Timeout));
+    assert!(outcome.event_count > 0);
+    assert!(!outcome.crashed_early);
+    assert!(!outcome.clipboard_alive);
+}
+
+/// The app closing its own window, rather than running out the clock.
+fn frame_limit() {
+    let outcome = run(ClipboardConfig {
+        frames: Some(3),
+        timeout: Duration::from_secs(10),
+        ..ClipboardConfig::default()
+    });
+    assert_eq!(outcome.shutdown_reason, Some(ShutdownReason::FrameLimit));
+    assert_eq!(outcome.frame_count, 3);
+    assert!(!outcome.clipboard_alive);
+}
+
+/// `--ordered-exit` drops a clipboard that really existed before the loop
+/// exits.
+fn ordered_exit() {
+    if !on_wayland() {
+        return;
+    }
+    let outcome = run(ClipboardConfig {
+        timeout: Duration::from_millis(300),
+        ..ClipboardConfig::default()
+    });
+    assert!(outcome.clipboard_created);
+    assert!(!outcome.clipboard_alive);
+}
+
+/// Without ordered exit the clipboard outlives the event loop, which
+/// `--assert-no-leak` reports instead of crashing on.
+fn forced_leak() {
+    if !on_wayland() {
+        return;
+    }
+    let outcome = run_once(ClipboardConfig {
+        timeout: Duration::from_millis(300),
+        assert_no_leak: true,
+        ..ClipboardConfig::default()
+    })
+    .expect("run failed");
+    assert!(outcome.clipboard_created);
+    assert!(outcome.clipboard_alive);
+}
+
+/// A window that can't be created ends the run cleanly instead of panicking.
+fn window_error() {
+    let outcome = run_with(ClipboardConfig::default(), |_, _| {
+        Err(std::io::Error::other("no window for this test"))
+    })
+    .expect("run failed");
+    assert_eq!(outcome.shutdown_reason, Some(ShutdownReason::Error));
+    assert_eq!(outcome.frame_count, 0);
+    assert!(outcome.crashed_early);
+    assert!(!outcome.clipboard_created);
+}
+
+/// The binary turns a `--assert-no-leak` failure into exit status 1.
+fn leak_exit_status() {
+    if !on_wayland() {
+        return;
+    }
+    let status = Command::new(env!("CARGO_BIN_EXE_smithay-clipboard-segfault"))
+        .args(["--assert-no-leak", "--timeout", "300"])
+        .status()
+        .expect("can't run the binary");
+    assert_eq!(status.code(), Some(1), "{status}");
+}
+
+/// Ctrl+C during `--watch-external` drops the clipboard before the window.
+fn watch_interrupted() {
+    if !on_wayland() {
+        return;
+    }
+    std::thread::spawn(|| {
+        std::thread::sleep(Duration::from_millis(300));
+        let status = Command::new("kill")
+            .args(["-INT", &std::process::id().to_string()])
+            .status()
+            .expect("can't run kill");
+        assert!(status.success(), "{status}");
+    });
+    let outcome = run_once(ClipboardConfig {
+        watch_external: true,
+        timeout: Duration::from_secs(10),
+        ..ClipboardConfig::default()
+    })
+    .expect("run failed");
+    assert_eq!(outcome.shutdown_reason, Some(ShutdownReason::Interrupted));
+    assert!(outcome.clipboard_created);
+    assert!(!outcome.clipboard_alive);
+}
+
+/// `--wait-timeout` gives up on an empty clipboard, and the binary would
+/// exit nonzero. Off Wayland there's no clipboard to wait on, which fails
+/// the run straight away instead.
+fn wait_timeout() {
+    let outcome = run(ClipboardConfig {
+        wait_for_owner: true,
+        wait_timeout: Some(Duration::from_millis(200)),
+        ..ClipboardConfig::default()
+    });
+    let reason = outcome.shutdown_reason.expect("no shutdown reason");
+    assert!(
+        matches!(reason, ShutdownReason::WaitTimeout | ShutdownReason::Error),
+        "{reason:?}"
+    );
+    assert!(reason.is_failure());
+    assert!(!outcome.clipboard_alive);
+}